@@ -1,9 +1,12 @@
+use core::convert::Infallible;
+
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     pubsub::{PubSubChannel, Publisher, Subscriber},
 };
+use embassy_time::{Instant, Timer};
 
-use crate::AsyncButtonDriver;
+use crate::{AsyncButtonDriver, ERROR_BACKOFF};
 
 /// 本地定义的异步ADC读取trait。
 pub trait AsyncAdc {
@@ -58,6 +61,12 @@ pub mod filter {
         }
     }
 
+    impl<const N: usize> Default for MedianFilter<N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl<const N: usize> AdcFilter for MedianFilter<N> {
         fn process(&mut self, new_sample: u16) -> Option<u16> {
             self.samples[self.index] = new_sample;
@@ -81,12 +90,23 @@ pub mod filter {
     }
 }
 
+/// 一个经过滤波的ADC采样值。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdcSample {
+    pub value: u16,
+    /// 滤波器输出该值的时间点。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::instant"))]
+    pub timestamp: Instant,
+}
+
 pub type AdcChannel<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> =
-    PubSubChannel<CriticalSectionRawMutex, u16, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+    PubSubChannel<CriticalSectionRawMutex, AdcSample, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 pub type AdcSubscriber<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> =
-    Subscriber<'a, CriticalSectionRawMutex, u16, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+    Subscriber<'a, CriticalSectionRawMutex, AdcSample, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 pub type AdcPublisher<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> =
-    Publisher<'a, CriticalSectionRawMutex, u16, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+    Publisher<'a, CriticalSectionRawMutex, AdcSample, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 
 /// 【按键工厂】用于创建简单的ADC按钮，可被克隆并在程序各处使用。
 #[derive(Clone)]
@@ -112,6 +132,7 @@ impl<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
             subscriber: self.channel.subscriber().unwrap(),
             threshold_low,
             threshold_high,
+            last_sample: None,
         }
     }
 }
@@ -161,12 +182,27 @@ impl<
         (driver, factory)
     }
 
+    /// 运行采样循环。ADC 读取出错时等待 [`ERROR_BACKOFF`] 后重试。
     pub async fn run(mut self) -> ! {
         loop {
-            if let Ok(raw_value) = self.adc.read().await {
-                if let Some(filtered_value) = self.filter.process(raw_value) {
-                    self.publisher.publish(filtered_value).await;
-                }
+            if self.try_run().await.is_err() {
+                Timer::after(ERROR_BACKOFF).await;
+            }
+        }
+    }
+
+    /// 运行采样循环，直到 ADC 读取返回错误。
+    ///
+    /// 出错后可以再次调用本方法继续采样。
+    pub async fn try_run(&mut self) -> Result<Infallible, ADC::Error> {
+        loop {
+            let raw_value = self.adc.read().await?;
+            if let Some(filtered_value) = self.filter.process(raw_value) {
+                let sample = AdcSample {
+                    value: filtered_value,
+                    timestamp: Instant::now(),
+                };
+                self.publisher.publish(sample).await;
             }
             self.filter.inter_sample_delay().await;
         }
@@ -178,25 +214,57 @@ pub struct SimpleAdcButton<'a, const MSG_CAP: usize, const SUBS: usize, const SU
     subscriber: AdcSubscriber<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     threshold_low: u16,
     threshold_high: u16,
+    /// 最近一次收到的采样值。
+    last_sample: Option<AdcSample>,
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
+    SimpleAdcButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    fn in_range(&self, value: u16) -> bool {
+        value >= self.threshold_low && value <= self.threshold_high
+    }
 }
 
 impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> AsyncButtonDriver
     for SimpleAdcButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
 {
-    async fn wait_for_press(&mut self) {
+    type Error = Infallible;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error> {
         loop {
-            let value = self.subscriber.next_message_pure().await;
-            if value >= self.threshold_low && value <= self.threshold_high {
-                return;
+            let sample = self.subscriber.next_message_pure().await;
+            self.last_sample = Some(sample);
+            if self.in_range(sample.value) {
+                return Ok(sample.timestamp);
             }
         }
     }
-    async fn wait_for_release(&mut self) {
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error> {
         loop {
-            let value = self.subscriber.next_message_pure().await;
-            if value < self.threshold_low || value > self.threshold_high {
-                return;
+            let sample = self.subscriber.next_message_pure().await;
+            self.last_sample = Some(sample);
+            if !self.in_range(sample.value) {
+                return Ok(sample.timestamp);
             }
         }
     }
+
+    /// 返回最新采样值是否落在阈值范围内。
+    ///
+    /// 会丢弃已积压的旧采样；如果还从未收到过采样，则等待下一个采样。
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error> {
+        while let Some(sample) = self.subscriber.try_next_message_pure() {
+            self.last_sample = Some(sample);
+        }
+        let sample = match self.last_sample {
+            Some(sample) => sample,
+            None => {
+                let sample = self.subscriber.next_message_pure().await;
+                self.last_sample = Some(sample);
+                sample
+            }
+        };
+        Ok(self.in_range(sample.value))
+    }
 }
\ No newline at end of file
@@ -1,27 +1,69 @@
+use core::{cell::Cell, convert::Infallible};
+
 use embassy_sync::{
-    blocking_mutex::raw::CriticalSectionRawMutex,
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
     pubsub::{PubSubChannel, Publisher, Subscriber},
 };
+use embassy_time::{Instant, Timer};
 
 use crate::{
     adc::{AdcFilter, AsyncAdc},
-    AsyncButtonDriver,
+    AsyncButtonDriver, ERROR_BACKOFF,
 };
 
 pub trait KeyDecoder {
     fn decode(&self, value: u16) -> u32;
 }
 
-pub type KeymaskChannel<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> =
-    PubSubChannel<CriticalSectionRawMutex, u32, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+/// 解码后的按键位掩码。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keymask {
+    /// 当前所有被按下按键的位掩码，第 n 位对应按键 n。
+    pub mask: u32,
+    /// 解码出该掩码的时间点。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::instant"))]
+    pub timestamp: Instant,
+}
+
+/// 解码任务与各按键之间共享的通道。
+///
+/// 除了广播每一次掩码变化，还保存最近一次发布的掩码，
+/// 使任何时候创建的按键都能立即知道自己的当前状态。
+pub struct KeymaskChannel<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> {
+    channel: PubSubChannel<CriticalSectionRawMutex, Keymask, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
+    /// 最近一次发布的掩码，第一次解码之前为 `None`。
+    latest: Mutex<CriticalSectionRawMutex, Cell<Option<Keymask>>>,
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
+    KeymaskChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    pub const fn new() -> Self {
+        Self {
+            channel: PubSubChannel::new(),
+            latest: Mutex::new(Cell::new(None)),
+        }
+    }
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> Default
+    for KeymaskChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 type KeymaskPublisher<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> =
-    Publisher<'a, CriticalSectionRawMutex, u32, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+    Publisher<'a, CriticalSectionRawMutex, Keymask, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 pub type KeymaskSubscriber<
     'a,
     const MSG_CAP: usize,
     const SUBS: usize,
     const SUBSCRIBER_CAP: usize,
-> = Subscriber<'a, CriticalSectionRawMutex, u32, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
+> = Subscriber<'a, CriticalSectionRawMutex, Keymask, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 
 
 #[derive(Clone)]
@@ -31,20 +73,30 @@ pub struct KeypadButtonFactory<
     const SUBS: usize,
     const SUBSCRIBER_CAP: usize,
 > {
-    mask_channel: &'a KeymaskChannel<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
+    mask_channel: &'a KeymaskChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
 }
 
 impl<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
     KeypadButtonFactory<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>
 {
     /// 在任何时候创建一个新的按键驱动实例。
+    ///
+    /// 如果解码任务已经发布过掩码，新按键以最近一次的掩码作为初始状态。
     pub fn button(&self, key_id: u8) -> KeypadButton<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP> {
         assert!(key_id < 32, "Key ID must be less than 32");
-        KeypadButton {
-            keymask_sub: self.mask_channel.subscriber().unwrap(),
+        let mut button = KeypadButton {
+            keymask_sub: self.mask_channel.channel.subscriber().unwrap(),
             key_mask: 1 << key_id,
             last_known_mask: 0,
+            synced: false,
+            last_change: Instant::from_ticks(0),
+        };
+        // 先订阅再读取快照：解码任务先更新快照再发布，
+        // 因此之后收到的掩码要么就是快照本身，要么比它更新
+        if let Some(keymask) = self.mask_channel.latest.lock(Cell::get) {
+            button.observe(keymask);
         }
+        button
     }
 }
 
@@ -60,7 +112,10 @@ pub struct KeypadDriver<
     adc: ADC,
     filter: F,
     decoder: D,
+    mask_channel: &'a KeymaskChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     mask_pub: KeymaskPublisher<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
+    /// 最近一次发布的掩码，初始值保证第一次解码结果总会被发布。
+    last_mask: u32,
 }
 
 impl<
@@ -91,29 +146,48 @@ impl<
             adc,
             filter,
             decoder,
-            mask_pub: mask_channel.publisher().unwrap(),
+            mask_channel,
+            mask_pub: mask_channel.channel.publisher().unwrap(),
+            last_mask: u32::MAX,
         };
         let factory = KeypadButtonFactory { mask_channel };
         (driver, factory)
     }
 
     /// 运行解码循环。这是您需要 spawn 到后台的唯一任务。
+    ///
+    /// ADC 读取出错时等待 [`ERROR_BACKOFF`] 后重试。
     pub async fn run(mut self) -> ! {
-        let mut last_mask = u32::MAX;
+        loop {
+            if self.try_run().await.is_err() {
+                Timer::after(ERROR_BACKOFF).await;
+            }
+        }
+    }
+
+    /// 运行解码循环，直到 ADC 读取返回错误。
+    ///
+    /// 出错后最近一次发布的掩码保持不变，可以再次调用本方法继续解码。
+    pub async fn try_run(&mut self) -> Result<Infallible, ADC::Error> {
         loop {
             let value = loop {
-                if let Ok(raw_value) = self.adc.read().await {
-                    if let Some(filtered_value) = self.filter.process(raw_value) {
-                        break filtered_value;
-                    }
+                let raw_value = self.adc.read().await?;
+                if let Some(filtered_value) = self.filter.process(raw_value) {
+                    break filtered_value;
                 }
                 self.filter.inter_sample_delay().await;
             };
 
             let current_mask = self.decoder.decode(value);
-            if current_mask != last_mask {
-                self.mask_pub.publish(current_mask).await;
-                last_mask = current_mask;
+            if current_mask != self.last_mask {
+                let keymask = Keymask {
+                    mask: current_mask,
+                    timestamp: Instant::now(),
+                };
+                trace!("keypad: publish {:?}", keymask);
+                self.mask_channel.latest.lock(|latest| latest.set(Some(keymask)));
+                self.mask_pub.publish(keymask).await;
+                self.last_mask = current_mask;
             }
         }
     }
@@ -123,34 +197,66 @@ pub struct KeypadButton<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSC
     keymask_sub: KeymaskSubscriber<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     key_mask: u32,
     last_known_mask: u32,
+    /// 是否已经收到过掩码。
+    synced: bool,
+    /// 最近一次收到的掩码的时间戳。
+    last_change: Instant,
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
+    KeypadButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    /// 记录一个掩码。
+    fn observe(&mut self, keymask: Keymask) {
+        self.last_known_mask = keymask.mask;
+        self.synced = true;
+        self.last_change = keymask.timestamp;
+    }
 }
 
 impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> AsyncButtonDriver
     for KeypadButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
 {
-    async fn wait_for_press(&mut self) {
+    type Error = Infallible;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error> {
         if (self.last_known_mask & self.key_mask) != 0 {
-            return;
+            return Ok(self.last_change);
         }
         loop {
-            let new_mask = self.keymask_sub.next_message_pure().await;
-            self.last_known_mask = new_mask;
-            if (new_mask & self.key_mask) != 0 {
-                return;
+            let keymask = self.keymask_sub.next_message_pure().await;
+            self.observe(keymask);
+            if (keymask.mask & self.key_mask) != 0 {
+                return Ok(keymask.timestamp);
             }
         }
     }
 
-    async fn wait_for_release(&mut self) {
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error> {
         if (self.last_known_mask & self.key_mask) == 0 {
-            return;
+            return Ok(self.last_change);
         }
         loop {
-            let new_mask = self.keymask_sub.next_message_pure().await;
-            self.last_known_mask = new_mask;
-            if (new_mask & self.key_mask) == 0 {
-                return;
+            let keymask = self.keymask_sub.next_message_pure().await;
+            self.observe(keymask);
+            if (keymask.mask & self.key_mask) == 0 {
+                return Ok(keymask.timestamp);
             }
         }
     }
+
+    /// 处理所有已到达的掩码后，返回最近一次已知的状态。
+    ///
+    /// 只有在解码任务还没有完成第一次解码时才会等待，等到第一次解码结果
+    /// （它总会被发布）为止，因此上电时已被按住的按键不会被当作一次新的按下。
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error> {
+        while let Some(keymask) = self.keymask_sub.try_next_message_pure() {
+            self.observe(keymask);
+        }
+        if !self.synced {
+            let keymask = self.keymask_sub.next_message_pure().await;
+            self.observe(keymask);
+        }
+        Ok((self.last_known_mask & self.key_mask) != 0)
+    }
 }
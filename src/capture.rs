@@ -0,0 +1,163 @@
+use core::convert::Infallible;
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::{Channel, Receiver, Sender},
+};
+use embassy_time::{Instant, Timer};
+
+use crate::{AsyncButtonDriver, ERROR_BACKOFF};
+
+/// 一次带时间戳的电平变化。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edge {
+    /// 变化后的电平，`true` 表示按下。
+    pub pressed: bool,
+    /// 底层驱动观察到该变化的时间点。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::instant"))]
+    pub timestamp: Instant,
+}
+
+pub type EdgeChannel<const CAP: usize> = Channel<CriticalSectionRawMutex, Edge, CAP>;
+type EdgeSender<'a, const CAP: usize> = Sender<'a, CriticalSectionRawMutex, Edge, CAP>;
+type EdgeReceiver<'a, const CAP: usize> = Receiver<'a, CriticalSectionRawMutex, Edge, CAP>;
+
+/// 【后台捕获器】持续等待底层驱动的电平变化，并把带时间戳的变化写入缓冲通道。
+///
+/// 直接使用驱动时，只有在 `Button::next_event` 正在等待时电平变化才会被观察到；
+/// 如果消费事件的任务正忙，期间的点击就会丢失。把驱动交给 `EdgeCapture`
+/// 在独立任务中运行后，所有变化都会按顺序缓存下来，由 [`BufferedButton`]
+/// 交给状态机回放，不会丢失点击。
+///
+/// 通道容量决定了消费者最多可以落后多少次电平变化（一次点击占两次）。
+/// 通道写满后捕获任务会等待，期间的变化可能丢失。开始捕获时还会先写入一条
+/// 初始状态记录。
+pub struct EdgeCapture<'a, T: AsyncButtonDriver, const CAP: usize> {
+    driver: T,
+    pressed: bool,
+    /// 是否已经查询过底层驱动的初始状态。
+    started: bool,
+    sender: EdgeSender<'a, CAP>,
+}
+
+impl<'a, T: AsyncButtonDriver, const CAP: usize> EdgeCapture<'a, T, CAP> {
+    /// 创建一个新的边沿捕获器及其关联的缓冲按键。
+    ///
+    /// # 返回
+    /// 一个元组，包含:
+    /// - `EdgeCapture`: 需要被 spawn 到后台任务中运行。
+    /// - `BufferedButton`: 实现了 `AsyncButtonDriver`，可直接包装进 `Button`。
+    pub fn new(driver: T, channel: &'a EdgeChannel<CAP>) -> (Self, BufferedButton<'a, CAP>) {
+        let capture = Self {
+            driver,
+            pressed: false,
+            started: false,
+            sender: channel.sender(),
+        };
+        let button = BufferedButton {
+            receiver: channel.receiver(),
+            pressed: false,
+            synced: false,
+        };
+        (capture, button)
+    }
+
+    /// 运行捕获循环。底层驱动出错时等待 [`ERROR_BACKOFF`] 后重试。
+    pub async fn run(mut self) -> ! {
+        loop {
+            if self.try_run().await.is_err() {
+                Timer::after(ERROR_BACKOFF).await;
+            }
+        }
+    }
+
+    /// 运行捕获循环，直到底层驱动返回错误。
+    ///
+    /// 第一次运行时会先查询底层驱动的当前状态，并把它作为第一条记录写入通道，
+    /// 因此上电时已被按住的按键不会被记录为一次按下，[`BufferedButton`]
+    /// 也能据此报告 [`ButtonEvent::HeldAtStartup`](crate::ButtonEvent::HeldAtStartup)。
+    ///
+    /// 出错后捕获器的状态保持不变，可以再次调用本方法继续捕获。
+    pub async fn try_run(&mut self) -> Result<Infallible, T::Error> {
+        if !self.started {
+            self.pressed = self.driver.is_pressed().await?;
+            let initial = Edge {
+                pressed: self.pressed,
+                timestamp: Instant::now(),
+            };
+            self.sender.send(initial).await;
+            self.started = true;
+        }
+        loop {
+            let timestamp = if self.pressed {
+                self.driver.wait_for_release().await?
+            } else {
+                self.driver.wait_for_press().await?
+            };
+            self.pressed = !self.pressed;
+            let edge = Edge {
+                pressed: self.pressed,
+                timestamp,
+            };
+            self.sender.send(edge).await;
+        }
+    }
+}
+
+/// 从缓冲通道中回放电平变化的按键驱动。
+pub struct BufferedButton<'a, const CAP: usize> {
+    receiver: EdgeReceiver<'a, CAP>,
+    /// 最近一次回放的电平。
+    pressed: bool,
+    /// 是否已经收到捕获任务写入的初始状态。
+    synced: bool,
+}
+
+impl<const CAP: usize> BufferedButton<'_, CAP> {
+    /// 还没有收到初始状态时，等待捕获任务写入的第一条记录。
+    async fn sync(&mut self) {
+        if !self.synced {
+            self.pressed = self.receiver.receive().await.pressed;
+            self.synced = true;
+        }
+    }
+}
+
+impl<const CAP: usize> AsyncButtonDriver for BufferedButton<'_, CAP> {
+    type Error = Infallible;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error> {
+        self.sync().await;
+        loop {
+            let edge = self.receiver.receive().await;
+            self.pressed = edge.pressed;
+            if edge.pressed {
+                return Ok(edge.timestamp);
+            }
+        }
+    }
+
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error> {
+        self.sync().await;
+        loop {
+            let edge = self.receiver.receive().await;
+            self.pressed = edge.pressed;
+            if !edge.pressed {
+                return Ok(edge.timestamp);
+            }
+        }
+    }
+
+    /// 返回尚未回放的电平变化发生之前的状态，不会消耗缓冲中的变化。
+    ///
+    /// 捕获任务还没有写入初始状态时，先等待它。
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error> {
+        self.sync().await;
+        Ok(match self.receiver.try_peek() {
+            Ok(edge) => !edge.pressed,
+            Err(_) => self.pressed,
+        })
+    }
+}
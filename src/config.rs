@@ -1,46 +1,788 @@
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    watch::{DynReceiver, Watch},
+};
 use embassy_time::Duration;
 
+/// 在任务之间共享的按钮配置，`N` 为最多可跟随它的按钮数量。
+///
+/// 设置任务通过 `sender().send(config)` 发布新配置，每个按钮通过
+/// [`Button::observe_config`](crate::Button::observe_config) 持有一个接收端，
+/// 即使正在等待 `next_event` 也会立即采用新配置。
+pub type ConfigWatch<const N: usize> = Watch<CriticalSectionRawMutex, ButtonConfig, N>;
+/// [`ConfigWatch`] 的接收端，由 `dyn_receiver()` 创建。
+pub type ConfigReceiver = DynReceiver<'static, ButtonConfig>;
+
+/// [`LongPressLevels`] 最多可容纳的阈值数量。
+pub const MAX_LONG_PRESS_LEVELS: usize = 4;
+
+/// 按住时长从小到大排列的多级长按阈值。
+///
+/// 第 `n` 个阈值（从 1 开始计数）被越过时产生 `LongPressLevel { level: n }`。
+/// 阈值从消抖确认按下开始计算，必须长于 `long_press_time`，等级只在进入长按之后才会报告。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LongPressLevels {
+    thresholds: [Duration; MAX_LONG_PRESS_LEVELS],
+    len: u8,
+}
+
+impl LongPressLevels {
+    /// 不包含任何阈值。
+    pub const NONE: Self = Self {
+        thresholds: [Duration::from_ticks(0); MAX_LONG_PRESS_LEVELS],
+        len: 0,
+    };
+
+    /// 从严格递增的阈值列表创建。
+    ///
+    /// 阈值数量超过 [`MAX_LONG_PRESS_LEVELS`] 或未严格递增时返回 `None`。
+    pub const fn new(thresholds: &[Duration]) -> Option<Self> {
+        if thresholds.len() > MAX_LONG_PRESS_LEVELS {
+            return None;
+        }
+        let mut levels = Self::NONE;
+        let mut i = 0;
+        while i < thresholds.len() {
+            if i > 0 && thresholds[i].as_ticks() <= thresholds[i - 1].as_ticks() {
+                return None;
+            }
+            levels.thresholds[i] = thresholds[i];
+            i += 1;
+        }
+        levels.len = thresholds.len() as u8;
+        Some(levels)
+    }
+
+    /// 阈值数量。
+    pub const fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 第 `level` 级（从 1 开始计数）的阈值。
+    pub const fn threshold(&self, level: usize) -> Option<Duration> {
+        if level == 0 || level > self.len() {
+            None
+        } else {
+            Some(self.thresholds[level - 1])
+        }
+    }
+
+    /// 按从小到大的顺序返回所有阈值。
+    pub fn as_slice(&self) -> &[Duration] {
+        &self.thresholds[..self.len()]
+    }
+}
+
+impl Default for LongPressLevels {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// 可单独启用或关闭的手势集合。
+///
+/// 关闭不需要的手势可以消除识别它们带来的延迟，例如关闭 [`MULTI_CLICK`](Self::MULTI_CLICK)
+/// 后 `Click` 会在释放确认后立即产生，而不必等待多击窗口结束。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Gestures(u8);
+
+impl Gestures {
+    /// 不识别任何手势（`Down` / `Up` 等原始事件不受影响）。
+    pub const NONE: Self = Self(0);
+    /// 单击 `Click`。
+    pub const CLICK: Self = Self(1 << 0);
+    /// 双击 `DoubleClick` 与多击 `MultipleClick`。
+    pub const MULTI_CLICK: Self = Self(1 << 1);
+    /// 长按 `LongPressStart` 及其后的保持、等级和释放事件。
+    pub const LONG_PRESS: Self = Self(1 << 2);
+    /// 点击后按住 `ClickAndHold` 及其后的保持和释放事件。
+    ///
+    /// 关闭时，点击后按住按普通长按报告（如果长按已启用）。
+    pub const CLICK_AND_HOLD: Self = Self(1 << 3);
+    /// 所有手势。
+    pub const ALL: Self = Self(0b1111);
+
+    /// 两个集合的并集。
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// 从集合中移除 `other` 中的手势。
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// 是否包含 `other` 中的所有手势。
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// 集合的位表示。
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// 从位表示创建，包含未知的手势位时返回 `None`。
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::ALL.0 == 0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// 点击类事件的报告方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClickReporting {
+    /// 等待多击窗口结束后只报告最终的点击次数（`Click`、`DoubleClick` 或 `MultipleClick`）。
+    #[default]
+    Deferred,
+    /// 每次释放确认后立即报告当前的点击次数，之后的按下再报告升级后的事件。
+    ///
+    /// 升级事件的 [`TimedEvent::supersedes`](crate::TimedEvent::supersedes) 为 `true`，
+    /// 表示它取代同一手势中先前报告的点击事件，而不是一次新的手势。
+    Optimistic,
+}
+
+/// 消抖算法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebounceMode {
+    /// 电平变化后等待一个消抖时间，期间电平保持不变才确认；任何抖动都会重新开始。
+    #[default]
+    Deferred,
+    /// 在第一个电平变化时立即确认，之后在消抖时间内忽略所有变化（锁定）。
+    ///
+    /// 没有确认延迟，但锁定期间的抖动和短暂脉冲不会被过滤，锁定结束时按当前电平补报。
+    Eager,
+    /// 积分（计数）消抖：新电平累计保持消抖时间才确认，抖动只扣除其持续时间而不会重新开始。
+    ///
+    /// 适合抖动时间较长、但总体趋势明确的按键。
+    Integrator,
+}
+
+/// 自适应消抖的范围。
+///
+/// 状态机测量每次电平变化中的抖动持续时间（相邻变化间隔短于当前消抖时间的一串变化），
+/// 维护一个抖动时长的估计值：更长的抖动立即被采纳，更短的抖动只让估计值缓慢回落。
+/// 实际使用的消抖时间为估计值的两倍，并限制在 `min` 与 `max` 之间；
+/// 初始时从 `max` 开始，以免在学习完成前误报点击。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdaptiveDebounce {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub min: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub max: Duration,
+}
+
+/// 故障检测的判定条件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaultDetection {
+    /// 电平持续为按下超过该时长时判定为卡键（短路或异物），释放后恢复。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub stuck_after: Duration,
+    /// 在 `chatter_window` 内出现至少这么多次电平变化时判定为触点抖动故障。
+    pub chatter_edges: u8,
+    /// 抖动故障的统计窗口；释放后保持这么长时间没有电平变化即恢复。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub chatter_window: Duration,
+    /// 故障期间是否丢弃除故障事件以外的所有事件。
+    ///
+    /// 故障之前已经报告开始的长按（或 `ClickAndHold`）仍会报告其释放事件，
+    /// 使开始与释放事件保持成对。开启时，故障恢复后手势识别从空闲状态重新开始。
+    pub suppress: bool,
+}
+
+/// 长按保持事件的加速曲线，以 `long_press_hold_interval` 为初始周期。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HoldAcceleration {
+    /// 固定周期。
+    #[default]
+    None,
+    /// 每产生 `every` 次保持事件，周期缩短 `step`，但不低于 `min`。
+    Linear {
+        every: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        step: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        min: Duration,
+    },
+    /// 每产生一次保持事件，周期变为原来的 `percent`%（小于 100 时加速），但不低于 `min`。
+    Factor {
+        percent: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        min: Duration,
+    },
+}
+
+impl HoldAcceleration {
+    /// 已经产生 `holds` 次保持事件后，到下一次保持事件的周期。
+    ///
+    /// 加速后的周期至少为一个 tick，以免保持事件在同一时刻无限产生。
+    pub fn interval(&self, initial: Duration, holds: u16) -> Duration {
+        match *self {
+            HoldAcceleration::None => initial,
+            HoldAcceleration::Linear { every, step, min } => {
+                let steps = (holds / every.max(1)) as u64;
+                let reduction = step.as_ticks().saturating_mul(steps);
+                let floor = min.as_ticks().max(1);
+                Duration::from_ticks(initial.as_ticks().saturating_sub(reduction).max(floor))
+            }
+            HoldAcceleration::Factor { percent, min } => {
+                let floor = min.as_ticks().max(1);
+                let mut ticks = initial.as_ticks();
+                if percent < 100 {
+                    // 每一步都严格减小，很快就会到达下限
+                    for _ in 0..holds {
+                        if ticks <= floor {
+                            break;
+                        }
+                        ticks = ticks * percent as u64 / 100;
+                    }
+                }
+                Duration::from_ticks(ticks.max(floor))
+            }
+        }
+    }
+}
+
+/// 消费者来不及处理、多个 `LongPressHold` 同时到期时的补发策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HoldCatchUp {
+    /// 逐个补发所有错过的保持事件。
+    #[default]
+    Burst,
+    /// 丢弃错过的保持事件，在当前时间只产生一次 `missed` 为 0 的保持事件，并从当前时间继续计时。
+    ///
+    /// 错过的保持事件之后还有尚未报告的长按等级时，改为在等级之前最后一个错过的时间点产生。
+    Skip,
+    /// 把错过的保持事件合并为一次，`missed` 报告被合并的次数。
+    Coalesce,
+}
+
 /// 定义按钮事件逻辑的通用配置参数。
-/// 
+///
 /// 这个结构体允许用户精细调整各种时间相关的行为，
 /// 例如消抖、双击间隔和长按检测时间。
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ButtonConfig {
-    /// 消抖持续时间。
-    /// 
+    /// 按下的消抖持续时间。
+    ///
     /// 在检测到第一次电平变化后，此时间段内的任何后续变化都将被忽略，
     /// 以防止物理按键的机械抖动产生误报。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub debounce: Duration,
 
+    /// 释放的消抖持续时间。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub release_debounce: Duration,
+
+    /// 消抖算法。
+    pub debounce_mode: DebounceMode,
+
+    /// 自适应消抖，开启后按学习到的抖动时长代替 `debounce` 和 `release_debounce`。
+    ///
+    /// 当前的估计值可通过 [`ButtonFsm::bounce_estimate`](crate::ButtonFsm::bounce_estimate) 查询。
+    pub adaptive_debounce: Option<AdaptiveDebounce>,
+
     /// 双击和多次点击的时间窗口。
-    /// 
+    ///
     /// 一次点击事件发生后，库会在此时间窗口内等待下一次点击。
     /// 如果在窗口内发生，则会被计为双击或多次点击。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub multi_click_window: Duration,
 
     /// 触发长按事件所需的持续时间。
-    /// 
+    ///
     /// 按键需要持续按下超过这个时长，才会触发 `LongPressStart` 事件。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub long_press_time: Duration,
-    
+
     /// 在长按状态下，重复触发 `LongPressHold` 事件的周期。
+    ///
+    /// 开启 [`long_press_hold_acceleration`](Self::long_press_hold_acceleration) 时为初始周期。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub long_press_hold_interval: Duration,
+
+    /// 保持事件的加速曲线，例如在两键菜单中快速调节 0–1000 的数值。
+    ///
+    /// 每个保持事件的序号由 [`TimedEvent::repeat`](crate::TimedEvent::repeat) 报告。
+    pub long_press_hold_acceleration: HoldAcceleration,
+
+    /// 多个保持事件同时到期时的补发策略。
+    pub hold_catch_up: HoldCatchUp,
+
+    /// 多级长按阈值，例如“菜单 / 关机 / 恢复出厂”分别对应 1s / 3s / 10s。
+    ///
+    /// 每越过一级产生一次 `LongPressLevel`，`LongPressRelease` 报告达到的最高等级。
+    pub long_press_levels: LongPressLevels,
+
+    /// 按住期间报告长按进度的周期，`None` 表示关闭（为零时同样视为关闭）。
+    ///
+    /// 开启后，从消抖确认按下到 `long_press_time` 之间每隔一个周期产生一次
+    /// `LongPressProgress`，适合“按住以确认”的进度环。此时每次按下都被视为一次
+    /// 按住确认：提前释放产生 `LongPressCancelled`，不再识别单击和多击。
+    /// 本次按下无法识别为长按（[`Gestures`] 关闭了相应手势）时不报告进度，按点击处理。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::option_duration"))]
+    pub long_press_progress_interval: Option<Duration>,
+
+    /// 点击类事件的报告方式。
+    pub click_reporting: ClickReporting,
+
+    /// 卡键与触点抖动故障检测，`None` 表示关闭。
+    pub fault_detection: Option<FaultDetection>,
+
+    /// 启用的手势。
+    pub gestures: Gestures,
+
+    /// 一次多击手势最多计入的点击次数（0 视为 1）。
+    ///
+    /// 达到该次数后，点击事件在释放确认时立即产生，不再等待多击窗口。
+    /// 关闭 [`Gestures::MULTI_CLICK`] 时等同于 1。
+    pub max_clicks: u8,
+
+    /// 是否收集 [`ButtonStats`](crate::ButtonStats) 使用统计。
+    pub collect_stats: bool,
+
+    /// 是否在消抖确认的按下和释放时刻额外产生 `Down` / `Up` 事件。
+    ///
+    /// 这些原始事件不会影响点击和长按的识别，适合需要即时视觉反馈的界面。
+    pub raw_events: bool,
 }
 
-impl Default for ButtonConfig {
-    /// 提供一套合理的默认配置。
-    /// 
-    /// - 消抖: 20ms
+impl ButtonConfig {
+    /// 一套合理的默认配置，也是 [`builder`](Self::builder) 的起点。
+    ///
+    /// - 消抖: 按下与释放均为 20ms，等待确认，不自适应
     /// - 多击窗口: 250ms
     /// - 长按阈值: 500ms
     /// - 长按连发周期: 100ms
+    /// - 保持事件加速: 无
+    /// - 保持事件补发: 逐个补发
+    /// - 多级长按: 无
+    /// - 长按进度: 关闭
+    /// - 点击报告方式: 延迟（等待多击窗口结束）
+    /// - 故障检测: 关闭
+    /// - 启用的手势: 全部
+    /// - 最大点击次数: 255
+    /// - 使用统计: 关闭
+    /// - 原始按下/释放事件: 关闭
+    pub const DEFAULT: Self = Self {
+        debounce: Duration::from_millis(20),
+        release_debounce: Duration::from_millis(20),
+        debounce_mode: DebounceMode::Deferred,
+        adaptive_debounce: None,
+        multi_click_window: Duration::from_millis(250),
+        long_press_time: Duration::from_millis(500),
+        long_press_hold_interval: Duration::from_millis(100),
+        long_press_hold_acceleration: HoldAcceleration::None,
+        hold_catch_up: HoldCatchUp::Burst,
+        long_press_levels: LongPressLevels::NONE,
+        long_press_progress_interval: None,
+        click_reporting: ClickReporting::Deferred,
+        fault_detection: None,
+        gestures: Gestures::ALL,
+        max_clicks: u8::MAX,
+        collect_stats: false,
+        raw_events: false,
+    };
+
+    /// 按键点击即响应的配置：锁定式消抖、乐观点击报告，适合菜单导航等对延迟敏感的界面。
+    pub const RESPONSIVE: Self = expect_valid(
+        Self::builder()
+            .debounce(Duration::from_millis(10))
+            .release_debounce(Duration::from_millis(10))
+            .debounce_mode(DebounceMode::Eager)
+            .multi_click_window(Duration::from_millis(200))
+            .long_press_time(Duration::from_millis(400))
+            .long_press_hold_interval(Duration::from_millis(80))
+            .click_reporting(ClickReporting::Optimistic)
+            .build(),
+    );
+
+    /// 面向行动不便或手部震颤用户的配置：积分消抖过滤震颤，多击窗口和长按阈值更宽松。
+    pub const ACCESSIBLE: Self = expect_valid(
+        Self::builder()
+            .debounce(Duration::from_millis(50))
+            .release_debounce(Duration::from_millis(80))
+            .debounce_mode(DebounceMode::Integrator)
+            .multi_click_window(Duration::from_millis(600))
+            .long_press_time(Duration::from_millis(1200))
+            .long_press_hold_interval(Duration::from_millis(300))
+            .max_clicks(2)
+            .build(),
+    );
+
+    /// 游戏手柄类配置：锁定式消抖，关闭多击以消除单击延迟，长按保持快速连发并逐渐加速。
+    pub const GAMING: Self = expect_valid(
+        Self::builder()
+            .debounce(Duration::from_millis(5))
+            .release_debounce(Duration::from_millis(5))
+            .debounce_mode(DebounceMode::Eager)
+            .gestures(Gestures::ALL.without(Gestures::MULTI_CLICK))
+            .long_press_time(Duration::from_millis(300))
+            .long_press_hold_interval(Duration::from_millis(60))
+            .long_press_hold_acceleration(HoldAcceleration::Factor {
+                percent: 80,
+                min: Duration::from_millis(20),
+            })
+            .hold_catch_up(HoldCatchUp::Skip)
+            .build(),
+    );
+
+    /// 戴手套操作的工业面板：较长的积分消抖和释放消抖，宽松的时间窗口，并检测卡键与触点抖动。
+    pub const INDUSTRIAL_GLOVE: Self = expect_valid(
+        Self::builder()
+            .debounce(Duration::from_millis(40))
+            .release_debounce(Duration::from_millis(60))
+            .debounce_mode(DebounceMode::Integrator)
+            .multi_click_window(Duration::from_millis(450))
+            .long_press_time(Duration::from_millis(800))
+            .long_press_hold_interval(Duration::from_millis(200))
+            .fault_detection(Some(FaultDetection {
+                stuck_after: Duration::from_secs(30),
+                chatter_edges: 20,
+                chatter_window: Duration::from_secs(1),
+                suppress: true,
+            }))
+            .build(),
+    );
+
+    /// 从 [`DEFAULT`](Self::DEFAULT) 开始构建配置。
+    pub const fn builder() -> ButtonConfigBuilder {
+        ButtonConfigBuilder {
+            config: Self::DEFAULT,
+        }
+    }
+
+    /// 检查各项时间参数是否相互一致。
+    pub const fn validate(&self) -> Result<(), ConfigError> {
+        let long_press = self.long_press_time.as_ticks();
+        if self.debounce.as_ticks() >= long_press || self.release_debounce.as_ticks() >= long_press
+        {
+            return Err(ConfigError::DebounceNotShorterThanLongPress);
+        }
+        if self.multi_click_window.as_ticks() < self.debounce.as_ticks() {
+            return Err(ConfigError::MultiClickWindowShorterThanDebounce);
+        }
+        if self.multi_click_window.as_ticks() <= self.release_debounce.as_ticks() {
+            return Err(ConfigError::MultiClickWindowNotLongerThanReleaseDebounce);
+        }
+        if self.long_press_hold_interval.as_ticks() == 0 {
+            return Err(ConfigError::ZeroHoldInterval);
+        }
+        if let Some(first) = self.long_press_levels.threshold(1) {
+            if first.as_ticks() <= long_press {
+                return Err(ConfigError::LongPressLevelNotAfterLongPress);
+            }
+        }
+        if let Some(adaptive) = self.adaptive_debounce {
+            if adaptive.min.as_ticks() > adaptive.max.as_ticks() {
+                return Err(ConfigError::InvalidAdaptiveDebounceRange);
+            }
+            if adaptive.max.as_ticks() >= long_press {
+                return Err(ConfigError::AdaptiveDebounceNotShorterThanLongPress);
+            }
+        }
+        if let Some(fault) = self.fault_detection {
+            if fault.chatter_edges == 0 {
+                return Err(ConfigError::ZeroChatterEdges);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ButtonConfig {
+    /// 与 [`ButtonConfig::DEFAULT`] 相同。
     fn default() -> Self {
-        Self {
-            debounce: Duration::from_millis(20),
-            multi_click_window: Duration::from_millis(250),
-            long_press_time: Duration::from_millis(500),
-            long_press_hold_interval: Duration::from_millis(100),
+        Self::DEFAULT
+    }
+}
+
+/// [`ButtonConfig`] 中相互矛盾的参数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// 按下或释放的消抖时间不短于长按阈值，长按会在消抖确认之前到期。
+    DebounceNotShorterThanLongPress,
+    /// 多击窗口短于消抖时间，第二次按下无法在窗口内完成消抖。
+    MultiClickWindowShorterThanDebounce,
+    /// 多击窗口不长于释放消抖时间，释放确认时窗口已经结束，永远无法识别多击。
+    MultiClickWindowNotLongerThanReleaseDebounce,
+    /// 长按保持周期为零，保持事件会在同一时刻无限产生。
+    ZeroHoldInterval,
+    /// 多级长按的阈值不长于长按阈值，等级会早于 `LongPressStart` 到期。
+    LongPressLevelNotAfterLongPress,
+    /// 自适应消抖的下限大于上限。
+    InvalidAdaptiveDebounceRange,
+    /// 自适应消抖的上限不短于长按阈值，长按会在消抖确认之前到期。
+    AdaptiveDebounceNotShorterThanLongPress,
+    /// 抖动故障的判定次数为零，任何电平变化都会被判定为故障。
+    ZeroChatterEdges,
+}
+
+/// 在 `const` 上下文中构建 [`ButtonConfig`] 的构建器，由 [`ButtonConfig::builder`] 创建。
+///
+/// ```
+/// use embassy_async_button::ButtonConfig;
+/// use embassy_time::Duration;
+///
+/// static CONFIG: ButtonConfig = match ButtonConfig::builder()
+///     .long_press_time(Duration::from_millis(800))
+///     .build()
+/// {
+///     Ok(config) => config,
+///     Err(_) => panic!("按钮配置无效"),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonConfigBuilder {
+    config: ButtonConfig,
+}
+
+impl ButtonConfigBuilder {
+    pub const fn debounce(mut self, debounce: Duration) -> Self {
+        self.config.debounce = debounce;
+        self
+    }
+
+    pub const fn release_debounce(mut self, release_debounce: Duration) -> Self {
+        self.config.release_debounce = release_debounce;
+        self
+    }
+
+    pub const fn debounce_mode(mut self, debounce_mode: DebounceMode) -> Self {
+        self.config.debounce_mode = debounce_mode;
+        self
+    }
+
+    pub const fn adaptive_debounce(mut self, adaptive_debounce: Option<AdaptiveDebounce>) -> Self {
+        self.config.adaptive_debounce = adaptive_debounce;
+        self
+    }
+
+    pub const fn multi_click_window(mut self, multi_click_window: Duration) -> Self {
+        self.config.multi_click_window = multi_click_window;
+        self
+    }
+
+    pub const fn long_press_time(mut self, long_press_time: Duration) -> Self {
+        self.config.long_press_time = long_press_time;
+        self
+    }
+
+    pub const fn long_press_hold_interval(mut self, long_press_hold_interval: Duration) -> Self {
+        self.config.long_press_hold_interval = long_press_hold_interval;
+        self
+    }
+
+    pub const fn long_press_hold_acceleration(mut self, acceleration: HoldAcceleration) -> Self {
+        self.config.long_press_hold_acceleration = acceleration;
+        self
+    }
+
+    pub const fn hold_catch_up(mut self, hold_catch_up: HoldCatchUp) -> Self {
+        self.config.hold_catch_up = hold_catch_up;
+        self
+    }
+
+    pub const fn long_press_levels(mut self, long_press_levels: LongPressLevels) -> Self {
+        self.config.long_press_levels = long_press_levels;
+        self
+    }
+
+    pub const fn long_press_progress_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.long_press_progress_interval = interval;
+        self
+    }
+
+    pub const fn click_reporting(mut self, click_reporting: ClickReporting) -> Self {
+        self.config.click_reporting = click_reporting;
+        self
+    }
+
+    pub const fn fault_detection(mut self, fault_detection: Option<FaultDetection>) -> Self {
+        self.config.fault_detection = fault_detection;
+        self
+    }
+
+    pub const fn gestures(mut self, gestures: Gestures) -> Self {
+        self.config.gestures = gestures;
+        self
+    }
+
+    pub const fn max_clicks(mut self, max_clicks: u8) -> Self {
+        self.config.max_clicks = max_clicks;
+        self
+    }
+
+    pub const fn collect_stats(mut self, collect_stats: bool) -> Self {
+        self.config.collect_stats = collect_stats;
+        self
+    }
+
+    pub const fn raw_events(mut self, raw_events: bool) -> Self {
+        self.config.raw_events = raw_events;
+        self
+    }
+
+    /// 检查参数并返回配置，参见 [`ButtonConfig::validate`]。
+    pub const fn build(self) -> Result<ButtonConfig, ConfigError> {
+        match self.config.validate() {
+            Ok(()) => Ok(self.config),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// 在编译期展开预设时，无效的参数会成为编译错误。
+const fn expect_valid(config: Result<ButtonConfig, ConfigError>) -> ButtonConfig {
+    match config {
+        Ok(config) => config,
+        Err(_) => panic!("invalid ButtonConfig preset"),
+    }
+}
+
+/// 带有不变量的类型手动实现 serde，反序列化时同样检查这些不变量。
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use core::fmt;
+
+    use embassy_time::Duration;
+    use serde::{
+        de::{Error, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    use super::{
+        AdaptiveDebounce, ButtonConfig, ClickReporting, DebounceMode, FaultDetection, Gestures,
+        HoldAcceleration, HoldCatchUp, LongPressLevels, MAX_LONG_PRESS_LEVELS,
+    };
+
+    /// [`ButtonConfig`] 的字段定义，只用于生成不检查参数的反序列化。
+    ///
+    /// 字段顺序必须与 `ButtonConfig` 相同；缺少字段时无法通过编译。
+    #[derive(Deserialize)]
+    #[serde(remote = "ButtonConfig")]
+    struct UncheckedConfig {
+        #[serde(with = "crate::serde_time::duration")]
+        debounce: Duration,
+        #[serde(with = "crate::serde_time::duration")]
+        release_debounce: Duration,
+        debounce_mode: DebounceMode,
+        adaptive_debounce: Option<AdaptiveDebounce>,
+        #[serde(with = "crate::serde_time::duration")]
+        multi_click_window: Duration,
+        #[serde(with = "crate::serde_time::duration")]
+        long_press_time: Duration,
+        #[serde(with = "crate::serde_time::duration")]
+        long_press_hold_interval: Duration,
+        long_press_hold_acceleration: HoldAcceleration,
+        hold_catch_up: HoldCatchUp,
+        long_press_levels: LongPressLevels,
+        #[serde(with = "crate::serde_time::option_duration")]
+        long_press_progress_interval: Option<Duration>,
+        click_reporting: ClickReporting,
+        fault_detection: Option<FaultDetection>,
+        gestures: Gestures,
+        max_clicks: u8,
+        collect_stats: bool,
+        raw_events: bool,
+    }
+
+    /// 与 `storage::decode` 一样，用 [`ButtonConfig::validate`] 检查反序列化得到的配置。
+    impl<'de> Deserialize<'de> for ButtonConfig {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let config = UncheckedConfig::deserialize(deserializer)?;
+            config
+                .validate()
+                .map_err(|err| D::Error::custom(format_args!("invalid button config: {err:?}")))?;
+            Ok(config)
+        }
+    }
+
+    /// 编码为位表示。
+    impl Serialize for Gestures {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u8(self.bits())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Gestures {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bits = u8::deserialize(deserializer)?;
+            Gestures::from_bits(bits).ok_or_else(|| D::Error::custom("unknown gesture bits"))
+        }
+    }
+
+    /// 编码为阈值微秒数的序列。
+    impl Serialize for LongPressLevels {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(
+                self.as_slice()
+                    .iter()
+                    .map(|threshold| threshold.as_micros()),
+            )
         }
     }
-}
\ No newline at end of file
+
+    impl<'de> Deserialize<'de> for LongPressLevels {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(LevelsVisitor)
+        }
+    }
+
+    struct LevelsVisitor;
+
+    impl<'de> Visitor<'de> for LevelsVisitor {
+        type Value = LongPressLevels;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "at most {MAX_LONG_PRESS_LEVELS} strictly increasing thresholds in microseconds"
+            )
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut thresholds = [Duration::from_ticks(0); MAX_LONG_PRESS_LEVELS];
+            let mut len = 0;
+            while let Some(micros) = seq.next_element::<u64>()? {
+                if len == MAX_LONG_PRESS_LEVELS {
+                    return Err(A::Error::invalid_length(len + 1, &self));
+                }
+                thresholds[len] = Duration::from_micros(micros);
+                len += 1;
+            }
+            LongPressLevels::new(&thresholds[..len])
+                .ok_or_else(|| A::Error::custom("long press thresholds are not increasing"))
+        }
+    }
+}
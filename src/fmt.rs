@@ -0,0 +1,14 @@
+/// 输出一条追踪日志：开启 `defmt` 时经由 `defmt::trace!`，开启 `log` 时经由 `log::trace!`，
+/// 两者都未开启时不产生任何代码。
+///
+/// 格式字符串只能使用 `defmt` 与 `log` 共同支持的 `{}` 和 `{:?}`。
+macro_rules! trace {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::trace!($s $(, $x)*);
+        #[cfg(feature = "log")]
+        ::log::trace!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "log")))]
+        let _ = ($(&$x),*);
+    }};
+}
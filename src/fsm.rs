@@ -0,0 +1,1182 @@
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    config::{ButtonConfig, ClickReporting, DebounceMode, Gestures, HoldCatchUp},
+    ButtonEvent, ButtonFault, ButtonStats, TimedEvent,
+};
+
+/// 内部事件队列的容量。
+///
+/// 单次状态转换最多产生少量事件；一次 `on_edge` 之前积压的定时器事件超过容量时，
+/// 电平变化会暂缓处理，直到调用方取出事件腾出空间。
+const QUEUE_CAP: usize = 8;
+
+/// 一次定时器到期最多产生的事件数（`Up` 与点击事件）。
+const FIRE_EVENTS: usize = 2;
+
+/// 应用一次电平变化最多产生的事件数（抖动故障、`Up`、释放事件与故障恢复）。
+const EDGE_EVENTS: usize = 4;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ButtonState {
+    Idle,
+    PressDebouncing {
+        count: u8,
+        /// 最近一次电平变化的时间点。
+        since: Instant,
+        /// 积分消抖在 `since` 之前已经累计的按下时长。
+        credit: Duration,
+    },
+    Pressed {
+        start_time: Instant,
+        count: u8,
+        /// 已经产生的 `LongPressProgress` 次数。
+        ticks: u16,
+    },
+    ReleaseDebouncing {
+        count: u8,
+        press_start: Instant,
+        release_start: Instant,
+        /// 最近一次电平变化的时间点。
+        since: Instant,
+        /// 积分消抖在 `since` 之前已经累计的释放时长。
+        credit: Duration,
+    },
+    WaitingForMultiClick {
+        count: u8,
+        last_release: Instant,
+        /// 最后一次按下的时长。
+        last_press: Duration,
+    },
+    LongPress {
+        /// 上一次长按事件（`LongPressStart` 或 `LongPressHold`）的时间点。
+        last_event: Instant,
+        press_start: Instant,
+        /// 已经产生的 `LongPressHold` 次数。
+        holds: u16,
+        /// 已经越过的多级长按等级。
+        level: u8,
+        /// 按住之前已完成的点击次数，不为 0 时为 `ClickAndHold` 手势。
+        clicks: u8,
+    },
+    /// 上电时就已被按住，等待释放。
+    HeldAtStartup {
+        since: Instant,
+    },
+}
+
+/// 状态转换的原因，只用于追踪日志。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    /// 启动时查询到的按键状态。
+    Startup,
+    /// 开始一次按下的消抖。
+    PressEdge,
+    /// 积分消抖期间的抖动，只调整累计时长。
+    Bounce,
+    /// 按下没有通过消抖。
+    DebounceRejected,
+    /// 按下通过消抖。
+    DebounceConfirmed,
+    /// 开始一次释放的消抖。
+    ReleaseEdge,
+    /// 释放没有通过消抖，回到按住状态。
+    ReleaseRejected,
+    /// 释放通过消抖，开始等待多击窗口。
+    ReleaseConfirmed,
+    /// 点击次数达到上限，不再等待多击窗口。
+    MaxClicksReached,
+    /// 开启长按进度时，按键在长按之前被释放。
+    LongPressCancelled,
+    /// 多击窗口结束，手势完成。
+    WindowExpired,
+    /// 按住时长达到长按阈值。
+    LongPressReached,
+    /// 长按或上电按住后释放。
+    Released,
+    /// 故障恢复，丢弃故障期间的手势。
+    FaultCleared,
+}
+
+/// 按钮当前所处的阶段，供 LED 等界面元素镜像按钮状态。
+///
+/// 与内部状态不同，阶段不暴露消抖细节：尚未确认的按下仍视为之前的阶段，
+/// 尚未确认的释放仍视为按住。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ButtonPhase {
+    /// 没有正在进行的手势。
+    Idle,
+    /// 按键被按住，尚未达到长按阈值。
+    ///
+    /// `clicks` 为包括本次按下在内的连击次数，`held` 为本次已按住的时长。
+    Pressed {
+        clicks: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        held: Duration,
+    },
+    /// 按键已释放，正在多击窗口内等待下一次按下。`clicks` 为已完成的点击次数。
+    WaitingForMultiClick { clicks: u8 },
+    /// 长按中，`held` 为已按住的时长。
+    LongPress {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        held: Duration,
+    },
+    /// 上电时就已被按住，`held` 为从检测到按住起的时长。
+    HeldAtStartup {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        held: Duration,
+    },
+}
+
+impl ButtonPhase {
+    /// 按键在该阶段是否处于按住状态。
+    pub fn is_pressed(&self) -> bool {
+        matches!(
+            self,
+            ButtonPhase::Pressed { .. }
+                | ButtonPhase::LongPress { .. }
+                | ButtonPhase::HeldAtStartup { .. }
+        )
+    }
+}
+
+/// 一个固定容量的环形事件队列，溢出时丢弃最新的事件。
+#[derive(Debug, Clone)]
+struct EventQueue {
+    buf: [Option<TimedEvent>; QUEUE_CAP],
+    head: usize,
+    len: usize,
+}
+
+impl EventQueue {
+    const fn new() -> Self {
+        Self {
+            buf: [None; QUEUE_CAP],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: TimedEvent) {
+        if self.len == QUEUE_CAP {
+            return;
+        }
+        self.buf[(self.head + self.len) % QUEUE_CAP] = Some(event);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<TimedEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.buf[self.head].take();
+        self.head = (self.head + 1) % QUEUE_CAP;
+        self.len -= 1;
+        event
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 队列是否还能容纳 `count` 个事件。
+    fn has_room(&self, count: usize) -> bool {
+        QUEUE_CAP - self.len >= count
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// 与 I/O 解耦的按钮状态机（sans-IO）。
+///
+/// `ButtonFsm` 不等待任何驱动或定时器，它只处理调用方提供的输入：
+/// - [`on_edge`](Self::on_edge)：报告一次带时间戳的电平变化；
+/// - [`poll`](Self::poll)：推进时间并取出已经产生的事件；
+/// - [`deadline`](Self::deadline)：下一次需要调用 `poll` 的时间点。
+///
+/// 因此它既可以被异步的 [`Button`](crate::Button) 驱动，也可以在 RTIC、
+/// 中断处理函数或阻塞式主循环中使用，并且可以用确定的时间戳进行单元测试。
+///
+/// 典型的驱动循环：
+/// 1. 反复调用 `poll(now)` 直到返回 `None`；
+/// 2. 等待下一次电平变化或 `deadline()`，以先到者为准；
+/// 3. 如果是电平变化，调用 `on_edge` 后回到第 1 步。
+#[derive(Debug, Clone)]
+pub struct ButtonFsm {
+    config: ButtonConfig,
+    state: ButtonState,
+    /// 状态机已经处理到的电平。
+    pressed: bool,
+    /// 已经报告、但因事件队列空间不足而尚未处理的电平变化。
+    pending: Option<(bool, Instant)>,
+    /// 最近一次交给状态转换处理的电平。只在 [`DebounceMode::Eager`] 的锁定期间与 `pressed` 不同。
+    level: bool,
+    /// [`DebounceMode::Eager`] 的锁定结束时间点。
+    lockout_until: Option<Instant>,
+    /// 自适应消抖学习到的抖动时长。
+    bounce_estimate: Duration,
+    /// 正在进行的一串抖动的第一次和最近一次电平变化时间点。
+    burst: Option<(Instant, Instant)>,
+    /// 当前的故障。
+    fault: Option<ButtonFault>,
+    /// 已经报告了 `LongPressStart` / `ClickAndHold`、尚未报告对应的释放事件。
+    hold_reported: bool,
+    /// 最近一次电平变化（或启动）的时间点。
+    last_change: Option<Instant>,
+    /// 抖动故障统计窗口的起点及窗口内的电平变化次数。
+    chatter: (Instant, u8),
+    stats: ButtonStats,
+    queue: EventQueue,
+}
+
+impl ButtonFsm {
+    /// 创建一个处于空闲状态、按键未按下的状态机。
+    pub const fn new(config: ButtonConfig) -> Self {
+        Self {
+            config,
+            state: ButtonState::Idle,
+            pressed: false,
+            pending: None,
+            level: false,
+            lockout_until: None,
+            bounce_estimate: initial_bounce_estimate(&config),
+            burst: None,
+            fault: None,
+            hold_reported: false,
+            last_change: None,
+            chatter: (Instant::from_ticks(0), 0),
+            stats: ButtonStats::new(),
+            queue: EventQueue::new(),
+        }
+    }
+
+    /// 报告启动时的按键状态。
+    ///
+    /// 如果按键在启动时已被按住，状态机不会把它当作一次新的按下，
+    /// 而是在释放时产生 [`ButtonEvent::HeldAtStartup`]。应在第一次 `on_edge` 之前调用。
+    pub fn on_startup(&mut self, pressed: bool, at: Instant) {
+        self.pressed = pressed;
+        self.pending = None;
+        self.level = pressed;
+        self.last_change = Some(at);
+        let state = if pressed {
+            ButtonState::HeldAtStartup { since: at }
+        } else {
+            ButtonState::Idle
+        };
+        self.transition(state, at, Reason::Startup);
+    }
+
+    /// 报告一次电平变化。
+    ///
+    /// `pressed` 为变化后的电平（`true` 表示按下），`at` 为观察到该变化的时间点。
+    /// 在 `at` 之前已经到期的定时器会先被处理，因此即使调用方来晚了，
+    /// 事件的先后顺序和时长计算也不受影响。与当前电平相同的重复报告会被忽略。
+    ///
+    /// 积压的定时器事件填满内部队列时，电平变化会在之后的 [`poll`](Self::poll) 或
+    /// [`take_event`](Self::take_event) 取出事件时继续处理，不会丢弃任何事件。
+    /// 调用方应在报告下一次电平变化之前取出所有事件，否则只能立即处理上一次变化，
+    /// 队列溢出的事件会被丢弃。
+    pub fn on_edge(&mut self, pressed: bool, at: Instant) {
+        if pressed == self.is_pressed() {
+            return;
+        }
+        if self.pending.is_some() {
+            self.advance_pending(true);
+        }
+        self.pending = Some((pressed, at));
+        self.advance_pending(false);
+    }
+
+    /// 处理暂缓的电平变化：先处理在它之前到期的定时器，再应用电平变化。
+    ///
+    /// 除非 `force`，队列空间不足时暂停，留待调用方取出事件后继续。
+    fn advance_pending(&mut self, force: bool) {
+        let Some((pressed, at)) = self.pending else {
+            return;
+        };
+        while let Some(deadline) = self.timer_deadline().filter(|&deadline| deadline <= at) {
+            if !force && !self.queue.has_room(FIRE_EVENTS) {
+                return;
+            }
+            self.fire(deadline, at);
+        }
+        if !force && !self.queue.has_room(EDGE_EVENTS) {
+            return;
+        }
+        self.pending = None;
+        self.apply_reported(pressed, at);
+    }
+
+    /// 应用一次已经越过所有更早定时器的电平变化。
+    fn apply_reported(&mut self, pressed: bool, at: Instant) {
+        self.pressed = pressed;
+        self.last_change = Some(at);
+        self.track_bounce(at);
+        self.track_chatter(at);
+        // 锁定期间只记录电平，锁定结束时再按当时的电平处理
+        let locked = self.lockout_until.is_some_and(|until| at < until);
+        let bounce = match self.state {
+            _ if locked => pressed != self.level,
+            ButtonState::PressDebouncing { .. } => !pressed,
+            ButtonState::ReleaseDebouncing { .. } => pressed,
+            _ => false,
+        };
+        if bounce {
+            self.record_stats(ButtonStats::record_bounce);
+        }
+        if !locked {
+            self.apply_level(pressed, at);
+        }
+        // 卡键在释放后恢复；先处理释放，使其产生的事件仍按故障期间处理
+        if !pressed && self.fault == Some(ButtonFault::Stuck) {
+            self.clear_fault(at);
+        }
+    }
+
+    /// 推进状态机到 `now`，并取出下一个事件。
+    ///
+    /// 返回 `None` 表示在下一次电平变化或 [`deadline`](Self::deadline) 之前不会再有事件。
+    pub fn poll(&mut self, now: Instant) -> Option<ButtonEvent> {
+        self.poll_timed(now).map(|timed| timed.event)
+    }
+
+    /// 与 [`poll`](Self::poll) 相同，但返回附带时间信息的事件。
+    pub fn poll_timed(&mut self, now: Instant) -> Option<TimedEvent> {
+        while self.queue.is_empty() {
+            if self.pending.is_some() {
+                self.advance_pending(false);
+                continue;
+            }
+            match self.timer_deadline() {
+                Some(deadline) if deadline <= now => self.fire(deadline, now),
+                _ => break,
+            }
+        }
+        self.queue.pop()
+    }
+
+    /// 取出一个已经产生的事件，但不推进时间。
+    ///
+    /// 当调用方还有尚未报告的（更早的）电平变化时，应使用它代替 `poll`，
+    /// 以免定时器越过这些电平变化先行到期。
+    pub fn take_event(&mut self) -> Option<ButtonEvent> {
+        self.take_timed_event().map(|timed| timed.event)
+    }
+
+    /// 与 [`take_event`](Self::take_event) 相同，但返回附带时间信息的事件。
+    pub fn take_timed_event(&mut self) -> Option<TimedEvent> {
+        if self.queue.is_empty() {
+            self.advance_pending(false);
+        }
+        self.queue.pop()
+    }
+
+    /// 当前状态下一个定时器的到期时间点，`None` 表示只需等待电平变化。
+    ///
+    /// 有暂缓处理的电平变化时，不晚于该变化的时间点。
+    pub fn deadline(&self) -> Option<Instant> {
+        let timer = self.timer_deadline();
+        match self.pending {
+            Some((_, at)) => Some(timer.map_or(at, |deadline| deadline.min(at))),
+            None => timer,
+        }
+    }
+
+    /// 下一个定时器的到期时间点，不考虑暂缓处理的电平变化。
+    fn timer_deadline(&self) -> Option<Instant> {
+        let lockout = self.lockout_until.filter(|_| self.pressed != self.level);
+        [self.state_deadline(), lockout, self.fault_deadline()]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// 使用统计的快照。未开启 [`ButtonConfig::collect_stats`] 时所有计数保持为 0。
+    pub fn stats(&self) -> ButtonStats {
+        self.stats
+    }
+
+    /// 清零使用统计。
+    pub fn reset_stats(&mut self) {
+        self.stats = ButtonStats::new();
+    }
+
+    /// 当前的故障，没有故障或未开启故障检测时返回 `None`。
+    pub fn fault(&self) -> Option<ButtonFault> {
+        self.fault
+    }
+
+    /// 当前状态自身的定时器到期时间点，不包括消抖锁定。
+    fn state_deadline(&self) -> Option<Instant> {
+        match self.state {
+            ButtonState::Idle | ButtonState::HeldAtStartup { .. } => None,
+            ButtonState::PressDebouncing { since, credit, .. } => {
+                Some(self.debounce_deadline(true, since, credit))
+            }
+            ButtonState::Pressed {
+                start_time,
+                count,
+                ticks,
+            } => {
+                let long_press_at = start_time + self.config.long_press_time;
+                let next_tick = self
+                    .hold_progress_interval(count)
+                    .map(|interval| start_time + interval * (ticks as u32 + 1))
+                    .filter(|&tick| tick < long_press_at);
+                let long_press = self.hold_gesture(count).map(|_| long_press_at);
+                match (next_tick, long_press) {
+                    (Some(tick), Some(long_press)) => Some(tick.min(long_press)),
+                    (tick, long_press) => tick.or(long_press),
+                }
+            }
+            ButtonState::ReleaseDebouncing { since, credit, .. } => {
+                Some(self.debounce_deadline(false, since, credit))
+            }
+            ButtonState::WaitingForMultiClick { last_release, .. } => {
+                Some(last_release + self.config.multi_click_window)
+            }
+            ButtonState::LongPress {
+                last_event,
+                press_start,
+                holds,
+                level,
+                clicks,
+            } => {
+                let next_hold = last_event + self.hold_interval(holds);
+                match self.next_level_at(press_start, level, clicks) {
+                    Some(next_level) if next_level < next_hold => Some(next_level),
+                    _ => Some(next_hold),
+                }
+            }
+        }
+    }
+
+    /// 查询 `now` 时刻所处的阶段。不会推进状态机，也不会消耗事件。
+    pub fn phase(&self, now: Instant) -> ButtonPhase {
+        match self.state {
+            ButtonState::Idle | ButtonState::PressDebouncing { count: 0, .. } => ButtonPhase::Idle,
+            ButtonState::PressDebouncing { count, .. }
+            | ButtonState::WaitingForMultiClick { count, .. } => {
+                ButtonPhase::WaitingForMultiClick { clicks: count }
+            }
+            ButtonState::Pressed {
+                start_time, count, ..
+            } => ButtonPhase::Pressed {
+                clicks: count,
+                held: now.saturating_duration_since(start_time),
+            },
+            ButtonState::ReleaseDebouncing {
+                count, press_start, ..
+            } => ButtonPhase::Pressed {
+                clicks: count,
+                held: now.saturating_duration_since(press_start),
+            },
+            ButtonState::LongPress { press_start, .. } => ButtonPhase::LongPress {
+                held: now.saturating_duration_since(press_start),
+            },
+            ButtonState::HeldAtStartup { since } => ButtonPhase::HeldAtStartup {
+                held: now.saturating_duration_since(since),
+            },
+        }
+    }
+
+    /// 最近一次报告的电平是否为按下。
+    ///
+    /// 驱动循环据此决定下一步等待按下还是释放。
+    pub fn is_pressed(&self) -> bool {
+        self.pending.map_or(self.pressed, |(pressed, _)| pressed)
+    }
+
+    /// 获取当前配置。
+    pub fn config(&self) -> &ButtonConfig {
+        &self.config
+    }
+
+    /// 替换配置。新的时间参数会立即作用于当前状态的定时器。
+    pub fn set_config(&mut self, config: ButtonConfig) {
+        if config.adaptive_debounce != self.config.adaptive_debounce {
+            self.bounce_estimate = initial_bounce_estimate(&config);
+            self.burst = None;
+        }
+        self.config = config;
+    }
+
+    /// 自适应消抖当前估计的抖动时长，未开启自适应消抖时返回 `None`。
+    ///
+    /// 实际使用的消抖时间为该估计值的两倍，并限制在配置的范围内。
+    pub fn bounce_estimate(&self) -> Option<Duration> {
+        self.config.adaptive_debounce.map(|_| self.bounce_estimate)
+    }
+
+    /// 重置到空闲状态并丢弃尚未取出的事件。
+    ///
+    /// 最近一次报告的电平会被保留，因此正被按住的按键需要先释放，
+    /// 才会开始一次新的按下。
+    pub fn reset(&mut self) {
+        trace!("button: reset from {:?}", self.state);
+        self.state = ButtonState::Idle;
+        self.pressed = self.is_pressed();
+        self.pending = None;
+        self.level = self.pressed;
+        self.lockout_until = None;
+        self.fault = None;
+        self.hold_reported = false;
+        self.chatter.1 = 0;
+        self.queue.clear();
+    }
+
+    /// 把电平变化交给状态转换处理，并在 [`DebounceMode::Eager`] 下开始锁定。
+    fn apply_level(&mut self, pressed: bool, at: Instant) {
+        self.level = pressed;
+        self.apply_edge(pressed, at);
+        if self.config.debounce_mode == DebounceMode::Eager {
+            self.lockout_until = Some(at + self.debounce_time(pressed));
+        }
+    }
+
+    fn apply_edge(&mut self, pressed: bool, at: Instant) {
+        let integrator = self.config.debounce_mode == DebounceMode::Integrator;
+        match (self.state, pressed) {
+            (ButtonState::Idle, true) => {
+                let state = ButtonState::PressDebouncing {
+                    count: 0,
+                    since: at,
+                    credit: Duration::from_ticks(0),
+                };
+                self.transition(state, at, Reason::PressEdge);
+            }
+            (
+                ButtonState::PressDebouncing {
+                    count,
+                    since,
+                    credit,
+                },
+                pressed,
+            ) if integrator => {
+                let state = ButtonState::PressDebouncing {
+                    count,
+                    since: at,
+                    credit: integrate(credit, since, at, !pressed),
+                };
+                self.transition(state, at, Reason::Bounce);
+            }
+            (ButtonState::PressDebouncing { .. }, false) => {
+                self.transition(ButtonState::Idle, at, Reason::DebounceRejected);
+            }
+            (
+                ButtonState::Pressed {
+                    start_time, count, ..
+                },
+                false,
+            ) => {
+                let state = ButtonState::ReleaseDebouncing {
+                    count,
+                    press_start: start_time,
+                    release_start: at,
+                    since: at,
+                    credit: Duration::from_ticks(0),
+                };
+                self.transition(state, at, Reason::ReleaseEdge);
+            }
+            (
+                ButtonState::ReleaseDebouncing {
+                    count,
+                    press_start,
+                    release_start,
+                    since,
+                    credit,
+                },
+                pressed,
+            ) if integrator => {
+                let state = ButtonState::ReleaseDebouncing {
+                    count,
+                    press_start,
+                    release_start,
+                    since: at,
+                    credit: integrate(credit, since, at, pressed),
+                };
+                self.transition(state, at, Reason::Bounce);
+            }
+            (
+                ButtonState::ReleaseDebouncing {
+                    count, press_start, ..
+                },
+                true,
+            ) => {
+                self.resume_pressed(count, press_start, at);
+            }
+            (ButtonState::WaitingForMultiClick { count, .. }, true) => {
+                let state = ButtonState::PressDebouncing {
+                    count,
+                    since: at,
+                    credit: Duration::from_ticks(0),
+                };
+                self.transition(state, at, Reason::PressEdge);
+            }
+            (
+                ButtonState::LongPress {
+                    press_start,
+                    holds,
+                    level,
+                    clicks,
+                    ..
+                },
+                false,
+            ) => {
+                self.transition(ButtonState::Idle, at, Reason::Released);
+                let held = at.saturating_duration_since(press_start);
+                self.record_stats(|stats| stats.record_release(held));
+                self.push_raw(ButtonEvent::Up, at, held);
+                let event = match clicks {
+                    0 => ButtonEvent::LongPressRelease { level },
+                    clicks => ButtonEvent::ClickAndHoldRelease { clicks },
+                };
+                self.emit(event, at, held, holds);
+            }
+            (ButtonState::HeldAtStartup { since }, false) => {
+                self.transition(ButtonState::Idle, at, Reason::Released);
+                let held = at.saturating_duration_since(since);
+                self.emit(ButtonEvent::HeldAtStartup { duration: held }, at, held, 0);
+            }
+            // 其余组合（例如空闲时的释放）不改变状态。
+            _ => {}
+        }
+    }
+
+    /// 释放消抖失败，回到按住状态。
+    fn resume_pressed(&mut self, count: u8, press_start: Instant, at: Instant) {
+        // 释放消抖期间错过的进度不再补发
+        let ticks = match self.progress_interval() {
+            Some(interval) => {
+                let held = at.saturating_duration_since(press_start);
+                (held.as_ticks() / interval.as_ticks()).min(u16::MAX as u64) as u16
+            }
+            None => 0,
+        };
+        let state = ButtonState::Pressed {
+            start_time: press_start,
+            count,
+            ticks,
+        };
+        self.transition(state, at, Reason::ReleaseRejected);
+    }
+
+    /// 处理在 `now` 到期的定时器。`latest` 为调用方报告的当前时间，不早于 `now`。
+    fn fire(&mut self, now: Instant, latest: Instant) {
+        if let Some(until) = self.lockout_until.filter(|&until| until <= now) {
+            self.lockout_until = None;
+            if self.pressed != self.level {
+                self.apply_level(self.pressed, until);
+                return;
+            }
+        }
+        if self.fault_deadline().is_some_and(|at| at <= now) {
+            self.fire_fault(now);
+            return;
+        }
+
+        match self.state {
+            ButtonState::Idle | ButtonState::HeldAtStartup { .. } => {}
+            // 积分消抖的累计时长耗尽，放弃这次按下
+            ButtonState::PressDebouncing { .. } if !self.pressed => {
+                self.transition(ButtonState::Idle, now, Reason::DebounceRejected);
+            }
+            ButtonState::ReleaseDebouncing {
+                count, press_start, ..
+            } if self.pressed => {
+                self.resume_pressed(count, press_start, now);
+            }
+            ButtonState::PressDebouncing { count, .. } => {
+                let state = ButtonState::Pressed {
+                    start_time: now,
+                    count: count + 1,
+                    ticks: 0,
+                };
+                self.transition(state, now, Reason::DebounceConfirmed);
+                self.record_stats(ButtonStats::record_press);
+                self.push_raw(ButtonEvent::Down, now, Duration::from_ticks(0));
+            }
+            ButtonState::Pressed {
+                start_time,
+                count,
+                ticks,
+            } => {
+                let held = now.saturating_duration_since(start_time);
+                if held < self.config.long_press_time {
+                    let ticks = ticks.saturating_add(1);
+                    self.state = ButtonState::Pressed {
+                        start_time,
+                        count,
+                        ticks,
+                    };
+                    let permille = held.as_ticks() * 1000 / self.config.long_press_time.as_ticks();
+                    self.emit(
+                        ButtonEvent::LongPressProgress {
+                            permille: permille as u16,
+                        },
+                        now,
+                        held,
+                        ticks,
+                    );
+                    return;
+                }
+
+                // 截止时间保证到达这里时 hold_gesture 一定有值
+                let clicks = self.hold_gesture(count).unwrap_or(0);
+                let state = ButtonState::LongPress {
+                    last_event: now,
+                    press_start: start_time,
+                    holds: 0,
+                    level: 0,
+                    clicks,
+                };
+                self.transition(state, now, Reason::LongPressReached);
+                let event = match clicks {
+                    0 => ButtonEvent::LongPressStart,
+                    clicks => ButtonEvent::ClickAndHold { clicks },
+                };
+                let supersedes = clicks > 0 && self.reported_early(clicks);
+                self.push(event, now, held, 0, supersedes);
+            }
+            ButtonState::ReleaseDebouncing {
+                count,
+                press_start,
+                release_start,
+                ..
+            } => {
+                let last_press = release_start.saturating_duration_since(press_start);
+                self.record_stats(|stats| stats.record_release(last_press));
+                if self.hold_progress_interval(count).is_some() {
+                    self.transition(ButtonState::Idle, now, Reason::LongPressCancelled);
+                    self.push_raw(ButtonEvent::Up, now, last_press);
+                    self.emit(ButtonEvent::LongPressCancelled, now, last_press, 0);
+                    return;
+                }
+                self.push_raw(ButtonEvent::Up, now, last_press);
+                // 达到最大点击次数后不可能再有下一次点击，无需等待多击窗口
+                let done = count >= self.max_clicks();
+                if done || self.optimistic() {
+                    self.emit_clicks(count, now, last_press);
+                }
+                if done {
+                    self.transition(ButtonState::Idle, now, Reason::MaxClicksReached);
+                    return;
+                }
+                let state = ButtonState::WaitingForMultiClick {
+                    count,
+                    last_release: release_start,
+                    last_press,
+                };
+                self.transition(state, now, Reason::ReleaseConfirmed);
+            }
+            ButtonState::WaitingForMultiClick {
+                count, last_press, ..
+            } => {
+                self.transition(ButtonState::Idle, now, Reason::WindowExpired);
+                // 乐观模式下最终的点击次数在释放时已经报告过
+                if !self.optimistic() {
+                    self.emit_clicks(count, now, last_press);
+                }
+            }
+            ButtonState::LongPress {
+                last_event,
+                press_start,
+                holds,
+                level,
+                clicks,
+            } => {
+                let held = now.saturating_duration_since(press_start);
+                // 等级阈值与保持事件同时到期时，先报告等级
+                let next_level = self.next_level_at(press_start, level, clicks);
+                if next_level.is_some_and(|at| at <= now) {
+                    let level = level + 1;
+                    self.state = ButtonState::LongPress {
+                        last_event,
+                        press_start,
+                        holds,
+                        level,
+                        clicks,
+                    };
+                    self.emit(ButtonEvent::LongPressLevel { level }, now, held, holds);
+                    return;
+                }
+
+                // 统计在 `latest` 之前同样已经到期的保持事件，但不越过下一个等级
+                let mut at = now;
+                let mut missed: u16 = 0;
+                if self.config.hold_catch_up != HoldCatchUp::Burst {
+                    let bound = match next_level {
+                        Some(level_at) => latest.min(Instant::from_ticks(
+                            level_at.as_ticks().saturating_sub(1),
+                        )),
+                        None => latest,
+                    };
+                    (at, missed) = self.catch_up_holds(at, holds, bound);
+                }
+
+                // 以最后一个到期的时间点作为下一次保持事件的起点；跳过时从当前时间重新计时，
+                // 但不越过下一个等级，以免等级事件的时间倒退
+                if self.config.hold_catch_up == HoldCatchUp::Skip
+                    && next_level.is_none_or(|level_at| latest < level_at)
+                {
+                    at = latest;
+                }
+                let holds = holds.saturating_add(1).saturating_add(missed);
+                self.state = ButtonState::LongPress {
+                    last_event: at,
+                    press_start,
+                    holds,
+                    level,
+                    clicks,
+                };
+                if self.config.hold_catch_up == HoldCatchUp::Skip {
+                    missed = 0;
+                }
+                let held = at.saturating_duration_since(press_start);
+                let event = match clicks {
+                    0 => ButtonEvent::LongPressHold { missed },
+                    clicks => ButtonEvent::ClickAndHoldRepeat { clicks, missed },
+                };
+                self.emit(event, at, held, holds);
+            }
+        }
+    }
+
+    /// 产生 `count` 次点击对应的事件。
+    fn emit_clicks(&mut self, count: u8, at: Instant, last_press: Duration) {
+        if let Some(event) = self.click_event(count) {
+            let supersedes = count > 1 && self.reported_early(count - 1);
+            self.push(event, at, last_press, 0, supersedes);
+        }
+    }
+
+    /// `count` 次点击对应的事件，被关闭的手势返回 `None`。
+    fn click_event(&self, count: u8) -> Option<ButtonEvent> {
+        let (gesture, event) = match count {
+            0 => return None,
+            1 => (Gestures::CLICK, ButtonEvent::Click),
+            2 => (Gestures::MULTI_CLICK, ButtonEvent::DoubleClick),
+            n => (
+                Gestures::MULTI_CLICK,
+                ButtonEvent::MultipleClick { count: n },
+            ),
+        };
+        self.config.gestures.contains(gesture).then_some(event)
+    }
+
+    /// 在乐观模式下，`count` 次点击是否已经在释放时报告过。
+    fn reported_early(&self, count: u8) -> bool {
+        self.optimistic() && self.click_event(count).is_some()
+    }
+
+    fn optimistic(&self) -> bool {
+        self.config.click_reporting == ClickReporting::Optimistic
+    }
+
+    /// 第 `count` 次按下按住到长按阈值时识别的手势：`Some(0)` 为普通长按，
+    /// `Some(clicks)` 为 `ClickAndHold`，`None` 表示相应手势已关闭、不需要长按定时器。
+    fn hold_gesture(&self, count: u8) -> Option<u8> {
+        let gestures = self.config.gestures;
+        if count > 1 && gestures.contains(Gestures::CLICK_AND_HOLD) {
+            Some(count - 1)
+        } else if gestures.contains(Gestures::LONG_PRESS) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// 实际生效的最大点击次数。
+    fn max_clicks(&self) -> u8 {
+        if self.config.gestures.contains(Gestures::MULTI_CLICK) {
+            self.config.max_clicks.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// 卡键判定或抖动故障恢复的到期时间点。
+    fn fault_deadline(&self) -> Option<Instant> {
+        let detection = self.config.fault_detection?;
+        let last_change = self.last_change?;
+        match self.fault {
+            None if self.pressed => Some(last_change + detection.stuck_after),
+            Some(ButtonFault::Chattering) if !self.pressed => {
+                Some(last_change + detection.chatter_window)
+            }
+            _ => None,
+        }
+    }
+
+    fn fire_fault(&mut self, now: Instant) {
+        match self.fault {
+            None => {
+                self.fault = Some(ButtonFault::Stuck);
+                let held = self.last_change.map_or(Duration::from_ticks(0), |since| {
+                    now.saturating_duration_since(since)
+                });
+                self.emit(ButtonEvent::Fault(ButtonFault::Stuck), now, held, 0);
+            }
+            Some(_) => self.clear_fault(now),
+        }
+    }
+
+    /// 统计窗口内的电平变化次数，过多时判定为抖动故障。
+    fn track_chatter(&mut self, at: Instant) {
+        let Some(detection) = self.config.fault_detection else {
+            return;
+        };
+        let (start, count) = self.chatter;
+        self.chatter = if at.saturating_duration_since(start) >= detection.chatter_window {
+            (at, 1)
+        } else {
+            (start, count.saturating_add(1))
+        };
+        if self.fault.is_none() && self.chatter.1 >= detection.chatter_edges {
+            self.fault = Some(ButtonFault::Chattering);
+            self.emit(
+                ButtonEvent::Fault(ButtonFault::Chattering),
+                at,
+                Duration::from_ticks(0),
+                0,
+            );
+        }
+    }
+
+    fn clear_fault(&mut self, at: Instant) {
+        self.fault = None;
+        self.chatter.1 = 0;
+        // 故障期间的手势已被丢弃，从空闲状态重新开始（此时按键一定处于释放状态）
+        if self
+            .config
+            .fault_detection
+            .is_some_and(|fault| fault.suppress)
+        {
+            self.transition(ButtonState::Idle, at, Reason::FaultCleared);
+            self.level = self.pressed;
+            self.lockout_until = None;
+        }
+        self.emit(ButtonEvent::FaultCleared, at, Duration::from_ticks(0), 0);
+    }
+
+    /// 按下（`pressed` 为 `true`）或释放的消抖时长。
+    fn debounce_time(&self, pressed: bool) -> Duration {
+        if let Some(adaptive) = self.config.adaptive_debounce {
+            let ticks = self.bounce_estimate.as_ticks().saturating_mul(2);
+            let ticks = ticks
+                .min(adaptive.max.as_ticks())
+                .max(adaptive.min.as_ticks());
+            return Duration::from_ticks(ticks);
+        }
+        if pressed {
+            self.config.debounce
+        } else {
+            self.config.release_debounce
+        }
+    }
+
+    /// 记录一次电平变化，并在一串抖动结束后更新抖动时长的估计值。
+    fn track_bounce(&mut self, at: Instant) {
+        let Some(adaptive) = self.config.adaptive_debounce else {
+            return;
+        };
+        let window = self.debounce_time(self.pressed);
+        self.burst = match self.burst {
+            Some((start, last)) if at.saturating_duration_since(last) < window => Some((start, at)),
+            Some((start, last)) => {
+                let sample = last.saturating_duration_since(start).as_ticks();
+                let estimate = self.bounce_estimate.as_ticks();
+                // 更长的抖动立即采纳，更短的抖动每次只回落 1/8
+                let estimate = if sample >= estimate {
+                    sample
+                } else {
+                    estimate - (estimate - sample) / 8
+                };
+                self.bounce_estimate = Duration::from_ticks(estimate.min(adaptive.max.as_ticks()));
+                Some((at, at))
+            }
+            None => Some((at, at)),
+        };
+    }
+
+    /// 消抖状态的到期时间点：电平为目标电平时在累计满消抖时长时确认，
+    /// 否则（仅积分消抖）在累计时长耗尽时放弃。锁定式消抖在电平变化时立即确认。
+    fn debounce_deadline(&self, target: bool, since: Instant, credit: Duration) -> Instant {
+        if self.pressed != target {
+            return since + credit;
+        }
+        let wait = match self.config.debounce_mode {
+            DebounceMode::Eager => Duration::from_ticks(0),
+            DebounceMode::Deferred | DebounceMode::Integrator => self.debounce_time(target),
+        };
+        since + Duration::from_ticks(wait.as_ticks().saturating_sub(credit.as_ticks()))
+    }
+
+    /// 已经产生 `holds` 次保持事件后，到下一次保持事件的周期。
+    ///
+    /// 未经 [`ButtonConfig::validate`] 检查的配置可能为零周期，这里同样至少取一个 tick。
+    /// 从 `at` 开始、已经产生 `holds` 次保持事件时，统计在 `bound`（含）之前同样已经到期的
+    /// 保持事件，返回最后一个到期的时间点及其数量（不超过 `u16::MAX`）。
+    ///
+    /// 加速曲线只在到达下限之前逐个计算，之后直接按固定周期相除，
+    /// 因此长时间没有轮询也不会逐个遍历所有错过的周期。
+    fn catch_up_holds(&self, mut at: Instant, holds: u16, bound: Instant) -> (Instant, u16) {
+        let floor = self.hold_interval(u16::MAX);
+        let mut missed: u16 = 0;
+        while missed < u16::MAX {
+            let interval = self.hold_interval(holds.saturating_add(missed.saturating_add(1)));
+            if interval == floor {
+                let ticks = interval.as_ticks();
+                let remaining = bound.saturating_duration_since(at).as_ticks() / ticks;
+                let skipped = remaining.min((u16::MAX - missed) as u64);
+                at += Duration::from_ticks(skipped * ticks);
+                missed += skipped as u16;
+                break;
+            }
+            let next = at + interval;
+            if next > bound {
+                break;
+            }
+            at = next;
+            missed += 1;
+        }
+        (at, missed)
+    }
+
+    fn hold_interval(&self, holds: u16) -> Duration {
+        let interval = self
+            .config
+            .long_press_hold_acceleration
+            .interval(self.config.long_press_hold_interval, holds);
+        Duration::from_ticks(interval.as_ticks().max(1))
+    }
+
+    /// 开启且不为零的长按进度周期。
+    fn progress_interval(&self) -> Option<Duration> {
+        self.config
+            .long_press_progress_interval
+            .filter(|interval| interval.as_ticks() > 0)
+    }
+
+    /// 第 `count` 次按下报告长按进度的周期。按住到长按阈值也不会识别出手势时不报告进度。
+    fn hold_progress_interval(&self, count: u8) -> Option<Duration> {
+        self.hold_gesture(count).and(self.progress_interval())
+    }
+
+    /// 下一个尚未越过的长按等级的到期时间点。多级长按只作用于普通长按，不作用于 `ClickAndHold`。
+    ///
+    /// 未经检查的配置中不长于长按阈值的等级在 `LongPressStart` 的同一时刻到期，
+    /// 使事件时间不会倒退。
+    fn next_level_at(&self, press_start: Instant, level: u8, clicks: u8) -> Option<Instant> {
+        if clicks > 0 {
+            return None;
+        }
+        self.config
+            .long_press_levels
+            .threshold(level as usize + 1)
+            .map(|threshold| press_start + threshold.max(self.config.long_press_time))
+    }
+
+    fn transition(&mut self, state: ButtonState, at: Instant, reason: Reason) {
+        trace!(
+            "button @{}ms: {:?} -> {:?} ({:?})",
+            at.as_millis(),
+            self.state,
+            state,
+            reason
+        );
+        self.state = state;
+    }
+
+    fn emit(&mut self, event: ButtonEvent, at: Instant, press_duration: Duration, repeat: u16) {
+        self.push(event, at, press_duration, repeat, false);
+    }
+
+    fn push(
+        &mut self,
+        event: ButtonEvent,
+        at: Instant,
+        press_duration: Duration,
+        repeat: u16,
+        supersedes: bool,
+    ) {
+        let suppress = self.fault.is_some()
+            && self
+                .config
+                .fault_detection
+                .is_some_and(|fault| fault.suppress);
+        let fault_event = matches!(event, ButtonEvent::Fault(_) | ButtonEvent::FaultCleared);
+        // 故障之前已经报告的长按仍然报告其释放，使开始与释放事件保持成对
+        let closes_hold = self.hold_reported
+            && matches!(
+                event,
+                ButtonEvent::LongPressRelease { .. } | ButtonEvent::ClickAndHoldRelease { .. }
+            );
+        if suppress && !fault_event && !closes_hold {
+            trace!(
+                "button @{}ms: {:?} suppressed by fault",
+                at.as_millis(),
+                event
+            );
+            return;
+        }
+        trace!("button @{}ms: emit {:?}", at.as_millis(), event);
+        match event {
+            ButtonEvent::LongPressStart | ButtonEvent::ClickAndHold { .. } => {
+                self.hold_reported = true
+            }
+            ButtonEvent::LongPressRelease { .. } | ButtonEvent::ClickAndHoldRelease { .. } => {
+                self.hold_reported = false
+            }
+            _ => {}
+        }
+        self.record_stats(|stats| stats.record_event(event, supersedes));
+        self.queue.push(TimedEvent {
+            event,
+            at,
+            press_duration,
+            repeat,
+            supersedes,
+        });
+    }
+
+    /// 在开启了使用统计时更新统计。
+    fn record_stats(&mut self, record: impl FnOnce(&mut ButtonStats)) {
+        if self.config.collect_stats {
+            record(&mut self.stats);
+        }
+    }
+
+    /// 在开启了原始事件时产生 `Down` / `Up`。
+    fn push_raw(&mut self, event: ButtonEvent, at: Instant, press_duration: Duration) {
+        if self.config.raw_events {
+            self.emit(event, at, press_duration, 0);
+        }
+    }
+}
+
+/// 自适应消抖的初始估计值，使实际消抖时间从上限开始。
+const fn initial_bounce_estimate(config: &ButtonConfig) -> Duration {
+    match config.adaptive_debounce {
+        Some(adaptive) => Duration::from_ticks(adaptive.max.as_ticks() / 2),
+        None => Duration::from_ticks(0),
+    }
+}
+
+/// 更新积分消抖的累计时长：`since` 到 `at` 之间电平为目标电平时累加，否则扣除。
+fn integrate(credit: Duration, since: Instant, at: Instant, at_target: bool) -> Duration {
+    let elapsed = at.saturating_duration_since(since).as_ticks();
+    let credit = credit.as_ticks();
+    Duration::from_ticks(if at_target {
+        credit.saturating_add(elapsed)
+    } else {
+        credit.saturating_sub(elapsed)
+    })
+}
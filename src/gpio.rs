@@ -1,4 +1,5 @@
 use crate::AsyncButtonDriver;
+use embassy_time::Instant;
 use embedded_hal::digital::InputPin;
 use embedded_hal_async::digital::Wait;
 
@@ -36,17 +37,28 @@ impl<P> AsyncButtonDriver for GpioButton<P>
 where
     P: InputPin + Wait,
 {
-    async fn wait_for_press(&mut self) {
+    type Error = P::Error;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error> {
+        match self.active_level {
+            ActiveLevel::Low => self.pin.wait_for_low().await?,
+            ActiveLevel::High => self.pin.wait_for_high().await?,
+        }
+        Ok(Instant::now())
+    }
+
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error> {
         match self.active_level {
-            ActiveLevel::Low => self.pin.wait_for_low().await.unwrap_or_default(),
-            ActiveLevel::High => self.pin.wait_for_high().await.unwrap_or_default(),
+            ActiveLevel::Low => self.pin.wait_for_high().await?,
+            ActiveLevel::High => self.pin.wait_for_low().await?,
         }
+        Ok(Instant::now())
     }
 
-    async fn wait_for_release(&mut self) {
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error> {
         match self.active_level {
-            ActiveLevel::Low => self.pin.wait_for_high().await.unwrap_or_default(),
-            ActiveLevel::High => self.pin.wait_for_low().await.unwrap_or_default(),
+            ActiveLevel::Low => self.pin.is_low(),
+            ActiveLevel::High => self.pin.is_high(),
         }
     }
 }
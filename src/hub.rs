@@ -0,0 +1,149 @@
+use core::{
+    future::{poll_fn, Future},
+    pin::pin,
+    task::Poll,
+};
+
+use crate::{AsyncButtonDriver, Button, TimedEvent};
+
+/// [`InputHub`] 产生的带标签事件。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HubEvent<Id> {
+    /// 全局序号，按事件被集线器取出的顺序从 0 开始递增（溢出时回绕）。
+    pub seq: u32,
+    /// 产生事件的按钮在注册时提供的 ID。
+    pub id: Id,
+    /// 按钮产生的事件及其时间信息，与 [`Button::next_timed_event`] 的返回值相同。
+    pub event: TimedEvent,
+}
+
+/// 可以注册到 [`InputHub`] 的一组按钮。
+///
+/// 为最多 8 个 `(Id, Button<T>)` 组成的元组实现，各个按钮的驱动类型可以互不相同，
+/// 但 ID 的类型必须相同。
+pub trait ButtonSet {
+    /// 用户为每个按钮提供的 ID 类型。
+    type Id: Copy;
+
+    /// 按钮数量。
+    const LEN: usize;
+
+    /// 第 `index` 个按钮的 ID。
+    fn id(&self, index: usize) -> Self::Id;
+
+    /// 同时等待所有按钮，返回最先产生事件的按钮序号及其事件。
+    ///
+    /// 多个按钮同时就绪时，从第 `start` 个按钮开始依次检查。
+    async fn next_timed_event(&mut self, start: usize) -> (usize, TimedEvent);
+}
+
+/// 把多个驱动类型不同的 [`Button`] 汇集为一个带标签的事件源。
+///
+/// 事件按被集线器取出的先后顺序返回并编号。多个按钮同时有事件时按轮转顺序取出，
+/// 而不是按 [`TimedEvent::at`] 排序：刚产生过事件的按钮排到最后，因此一个持续产生事件的
+/// 按钮（例如长按保持）不会让其他按钮的事件一直等待。需要按发生时间排列时请使用 `at`。
+///
+/// ```ignore
+/// let mut hub = InputHub::new((
+///     (Input::Power, Button::new(gpio, ButtonConfig::default())),
+///     (Input::Menu, Button::new(matrix_key, ButtonConfig::RESPONSIVE)),
+/// ));
+/// loop {
+///     let HubEvent { id, event, .. } = hub.next_event().await;
+///     handle(id, event.event);
+/// }
+/// ```
+pub struct InputHub<B: ButtonSet> {
+    buttons: B,
+    /// 下一次最先检查的按钮序号。
+    start: usize,
+    seq: u32,
+}
+
+impl<B: ButtonSet> InputHub<B> {
+    pub fn new(buttons: B) -> Self {
+        Self {
+            buttons,
+            start: 0,
+            seq: 0,
+        }
+    }
+
+    /// 等待任意一个按钮的下一个事件。
+    ///
+    /// 与 [`Button::next_event`] 一样，驱动出错时退避后重试。放弃本方法返回的 future
+    /// 不会丢失任何按钮正在识别的手势。
+    pub async fn next_event(&mut self) -> HubEvent<B::Id> {
+        let (index, event) = self.buttons.next_timed_event(self.start).await;
+        self.start = (index + 1) % B::LEN;
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        HubEvent {
+            seq,
+            id: self.buttons.id(index),
+            event,
+        }
+    }
+
+    /// 获取注册的按钮的不可变引用
+    pub fn buttons(&self) -> &B {
+        &self.buttons
+    }
+
+    /// 获取注册的按钮的可变引用，例如用于修改某个按钮的配置
+    pub fn buttons_mut(&mut self) -> &mut B {
+        &mut self.buttons
+    }
+
+    /// 取回注册的按钮。
+    pub fn into_inner(self) -> B {
+        self.buttons
+    }
+}
+
+macro_rules! impl_button_set {
+    ($len:literal; $($T:ident $fut:ident $idx:tt),+) => {
+        impl<Id: Copy, $($T: AsyncButtonDriver),+> ButtonSet for ($((Id, Button<$T>),)+) {
+            type Id = Id;
+            const LEN: usize = $len;
+
+            fn id(&self, index: usize) -> Id {
+                match index {
+                    $($idx => self.$idx.0,)+
+                    _ => panic!("button index out of range"),
+                }
+            }
+
+            // 单个按钮的元组中 `% 1` 恒为 0，属于预期行为
+            #[allow(clippy::modulo_one)]
+            async fn next_timed_event(&mut self, start: usize) -> (usize, TimedEvent) {
+                $(let mut $fut = pin!(self.$idx.1.next_timed_event());)+
+                poll_fn(|cx| {
+                    for offset in 0..Self::LEN {
+                        let index = (start + offset) % Self::LEN;
+                        let polled = match index {
+                            $($idx => $fut.as_mut().poll(cx),)+
+                            _ => unreachable!(),
+                        };
+                        if let Poll::Ready(event) = polled {
+                            return Poll::Ready((index, event));
+                        }
+                    }
+                    Poll::Pending
+                })
+                .await
+            }
+        }
+    };
+}
+
+impl_button_set!(1; T0 f0 0);
+impl_button_set!(2; T0 f0 0, T1 f1 1);
+impl_button_set!(3; T0 f0 0, T1 f1 1, T2 f2 2);
+impl_button_set!(4; T0 f0 0, T1 f1 1, T2 f2 2, T3 f3 3);
+impl_button_set!(5; T0 f0 0, T1 f1 1, T2 f2 2, T3 f3 3, T4 f4 4);
+impl_button_set!(6; T0 f0 0, T1 f1 1, T2 f2 2, T3 f3 3, T4 f4 4, T5 f5 5);
+impl_button_set!(7; T0 f0 0, T1 f1 1, T2 f2 2, T3 f3 3, T4 f4 4, T5 f5 5, T6 f6 6);
+impl_button_set!(8; T0 f0 0, T1 f1 1, T2 f2 2, T3 f3 3, T4 f4 4, T5 f5 5, T6 f6 6, T7 f7 7);
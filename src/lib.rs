@@ -1,192 +1,290 @@
 #![no_std]
 #![allow(async_fn_in_trait)]
 
+#[macro_use]
+mod fmt;
+
 pub mod adc;
 pub mod adc_keypad;
+pub mod capture;
 pub mod config;
+pub mod fsm;
 pub mod gpio;
+pub mod hub;
 pub mod matrix;
+/// `embassy_time` 时间类型的 serde 编码，供 `#[serde(with = "...")]` 使用。
+///
+/// 时长和时间点都编码为 `u64` 微秒数（时间点为自启动以来的微秒数），与设备的
+/// tick 频率无关，因此不同固件或上位机之间交换的数据含义一致。tick 频率不是 1MHz
+/// 的整数倍时，解码会把微秒数舍入到 tick。
+#[cfg(feature = "serde")]
+pub mod serde_time;
+pub mod stats;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "futures-core")]
+mod stream;
 
 pub use config::*;
-use embassy_futures::select::{select, Either};
-use embassy_time::{Instant, Timer};
+use core::future::pending;
+use embassy_futures::select::{select3, Either3};
+use embassy_time::{Duration, Instant, Timer};
+pub use fsm::{ButtonFsm, ButtonPhase};
+pub use hub::{HubEvent, InputHub};
+pub use stats::ButtonStats;
 
-use crate::config::ButtonConfig;
+/// 硬件出错后重试前的退避时间。
+///
+/// 本库对硬件错误采用统一的策略：`try_*` 方法（[`Button::try_next_event`]
+/// 以及各后台驱动的 `try_run`）立即把错误返回给调用方；不带 `try_` 的方法
+/// （[`Button::next_event`] 以及各后台驱动的 `run`）等待该时间后重试，
+/// 既不吞掉错误导致误报事件，也不会因为故障硬件而忙等。
+pub const ERROR_BACKOFF: Duration = Duration::from_millis(10);
 
 /// 一个trait，抽象了所有可以提供异步“按下”和“释放”事件的硬件源。
+///
+/// 两个方法都返回驱动观察到该电平变化的时间点。驱动应尽可能在靠近硬件的位置
+/// 打上时间戳（例如扫描任务读取引脚的时刻），而不是在调用方被调度之后，
+/// 这样即使消费事件的任务来晚了，按下时长和多击窗口也能被正确计算。
+///
+/// 读取硬件失败时返回 `Err`，此时不应认为发生了电平变化。
 pub trait AsyncButtonDriver {
-    async fn wait_for_press(&mut self);
-    async fn wait_for_release(&mut self);
+    /// 驱动读取硬件时可能产生的错误。
+    type Error;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error>;
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error>;
+
+    /// 查询按键当前是否处于按下状态。
+    ///
+    /// `Button` 在第一次等待事件前调用它，以识别上电时就已被按住的按键。
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error>;
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonEvent {
+    /// 消抖确认的按下（需开启 [`ButtonConfig::raw_events`]）。
+    Down,
+    /// 消抖确认的释放（需开启 [`ButtonConfig::raw_events`]）。
+    Up,
     Click,
     DoubleClick,
-    MultipleClick { count: u8 },
-    LongPressStart,
-    LongPressHold,
-    LongPressRelease,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ButtonState {
-    Idle,
-    PressDebouncing {
+    MultipleClick {
         count: u8,
-        start_time: Instant,
     },
-    Pressed {
-        start_time: Instant,
-        count: u8,
+    /// 尚未进入长按时的按住进度（需开启 [`ButtonConfig::long_press_progress_interval`]），
+    /// `permille` 为已按住时长占 `long_press_time` 的千分比。
+    LongPressProgress {
+        permille: u16,
     },
-    ReleaseDebouncing {
-        count: u8,
-        press_start: Instant,
-        release_start: Instant,
+    /// 开启长按进度时，按键在达到 `long_press_time` 之前被释放。取代本应产生的 `Click`。
+    LongPressCancelled,
+    LongPressStart,
+    /// 长按期间按 `long_press_hold_interval` 周期产生（开启加速曲线时周期逐渐缩短）。
+    ///
+    /// `missed` 为按 [`HoldCatchUp::Coalesce`] 合并进本次事件的、错过的保持事件数，
+    /// 其他策略下为 0。
+    LongPressHold {
+        missed: u16,
     },
-    WaitingForMultiClick {
-        count: u8,
-        last_release: Instant,
+    /// 按住时长越过了 [`ButtonConfig::long_press_levels`] 中的第 `level` 级阈值（从 1 开始计数）。
+    LongPressLevel {
+        level: u8,
+    },
+    /// 长按结束，`level` 为达到的最高等级，未越过任何等级时为 0。
+    LongPressRelease {
+        level: u8,
+    },
+    /// 在多击窗口内再次按下并按住到 `long_press_time`，`clicks` 为按住之前已完成的点击次数
+    /// （例如“单击后按住”为 1）。取代 `LongPressStart`；延迟报告模式下之前的点击不会再单独报告。
+    ClickAndHold {
+        clicks: u8,
+    },
+    /// `ClickAndHold` 之后按 `long_press_hold_interval` 周期产生，对应 `LongPressHold`。
+    ClickAndHoldRepeat {
+        clicks: u8,
+        missed: u16,
+    },
+    /// `ClickAndHold` 结束，对应 `LongPressRelease`。
+    ClickAndHoldRelease {
+        clicks: u8,
     },
-    LongPress {
-        start_time: Instant,
+    /// 检测到按键故障（需开启 [`ButtonConfig::fault_detection`]）。
+    Fault(ButtonFault),
+    /// 此前报告的故障已经恢复。
+    FaultCleared,
+    /// 上电时就已被按住的按键被释放，`duration` 为从检测到按住到释放的时长。
+    ///
+    /// 这次按住不会再产生点击或长按事件。
+    HeldAtStartup {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+        duration: Duration,
     },
 }
 
+/// 按键故障的种类。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ButtonFault {
+    /// 按键持续按下超过 [`FaultDetection::stuck_after`]。
+    Stuck,
+    /// 短时间内电平变化过于频繁，见 [`FaultDetection::chatter_edges`]。
+    Chattering,
+}
+
+/// 附带时间信息的按钮事件，由 [`Button::next_timed_event`] 返回。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedEvent {
+    pub event: ButtonEvent,
+    /// 事件发生的时间点（由定时器确认的事件为定时器到期时刻，由释放直接触发的事件为释放时刻）。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::instant"))]
+    pub at: Instant,
+    /// 相关按下的时长，从消抖确认按下开始计算。
+    ///
+    /// 点击类事件和 `Up` 为最后一次按下的时长；长按类事件为到事件发生时为止的按住时长；
+    /// `HeldAtStartup` 与其 `duration` 相同；`Fault(Stuck)` 为已按住的时长；
+    /// `Down` 与其余故障事件为 0。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub press_duration: Duration,
+    /// `LongPressHold`、`ClickAndHoldRepeat` 与 `LongPressProgress` 的序号（从 1 开始，
+    /// 被跳过或合并的保持事件同样计数）；
+    /// `LongPressLevel`、`LongPressRelease` 与 `ClickAndHoldRelease` 为此前保持事件的总数；
+    /// 其余事件为 0。
+    pub repeat: u16,
+    /// 本事件是否取代同一手势中先前报告的事件。
+    ///
+    /// 只在 [`ClickReporting::Optimistic`] 下可能为 `true`：例如先报告的 `Click`
+    /// 被随后的 `DoubleClick` 或 `ClickAndHold { clicks: 1 }` 取代。为 `false` 时表示一次新的手势
+    /// 或对先前事件的补充。
+    pub supersedes: bool,
+}
+
+/// 异步按钮：用 [`AsyncButtonDriver`] 等待电平变化，用 `embassy_time` 等待定时器，
+/// 事件识别逻辑全部委托给 [`ButtonFsm`]。
 pub struct Button<T: AsyncButtonDriver> {
     driver: T,
-    config: ButtonConfig,
-    state: ButtonState,
+    fsm: ButtonFsm,
+    /// 是否已经查询过上电时的按键状态。
+    started: bool,
+    /// 跟随的共享配置。
+    config_rx: Option<ConfigReceiver>,
 }
 
 impl<T: AsyncButtonDriver> Button<T> {
     pub fn new(driver: T, config: ButtonConfig) -> Self {
         Self {
             driver,
-            config,
-            state: ButtonState::Idle,
+            fsm: ButtonFsm::new(config),
+            started: false,
+            config_rx: None,
         }
     }
 
+    /// 等待下一个按钮事件。
+    ///
+    /// 驱动出错时等待 [`ERROR_BACKOFF`] 后重试；需要感知错误时请使用
+    /// [`try_next_event`](Self::try_next_event)。
     pub async fn next_event(&mut self) -> ButtonEvent {
+        self.next_timed_event().await.event
+    }
+
+    /// 等待下一个按钮事件，并把驱动错误返回给调用方。
+    ///
+    /// 出错时状态机保持不变，之后可以再次调用本方法继续识别。
+    pub async fn try_next_event(&mut self) -> Result<ButtonEvent, T::Error> {
+        Ok(self.try_next_timed_event().await?.event)
+    }
+
+    /// 与 [`next_event`](Self::next_event) 相同，但返回附带时间戳、按下时长和
+    /// 长按保持序号的事件。
+    pub async fn next_timed_event(&mut self) -> TimedEvent {
         loop {
-            match self.state {
-                ButtonState::Idle => {
-                    self.driver.wait_for_press().await;
-                    self.state = ButtonState::PressDebouncing {
-                        count: 0,
-                        start_time: Instant::now(),
-                    };
-                }
+            match self.try_next_timed_event().await {
+                Ok(event) => return event,
+                Err(_) => Timer::after(ERROR_BACKOFF).await,
+            }
+        }
+    }
 
-                ButtonState::PressDebouncing { count, start_time } => {
-                    let debounce_timer = Timer::at(start_time + self.config.debounce);
-                    match select(self.driver.wait_for_release(), debounce_timer).await {
-                        Either::First(_) => {
-                            self.state = ButtonState::Idle;
-                        }
-                        Either::Second(_) => {
-                            self.state = ButtonState::Pressed {
-                                start_time: Instant::now(),
-                                count: count + 1,
-                            };
-                        }
-                    }
-                }
+    /// 与 [`try_next_event`](Self::try_next_event) 相同，但返回附带时间信息的事件。
+    pub async fn try_next_timed_event(&mut self) -> Result<TimedEvent, T::Error> {
+        if !self.started {
+            let pressed = self.driver.is_pressed().await?;
+            self.fsm.on_startup(pressed, Instant::now());
+            self.started = true;
+        }
 
-                ButtonState::Pressed { start_time, count } => {
-                    let long_press_timer = Timer::at(start_time + self.config.long_press_time);
-                    match select(self.driver.wait_for_release(), long_press_timer).await {
-                        Either::First(_) => {
-                            self.state = ButtonState::ReleaseDebouncing {
-                                count,
-                                press_start: start_time,
-                                release_start: Instant::now(),
-                            };
-                        }
-                        Either::Second(_) => {
-                            self.state = ButtonState::LongPress {
-                                start_time,
-                            };
-                            return ButtonEvent::LongPressStart;
-                        }
-                    }
-                }
+        loop {
+            if let Some(event) = self.fsm.take_timed_event() {
+                return Ok(event);
+            }
 
-                ButtonState::ReleaseDebouncing { count, press_start, release_start } => {
-                    let debounce_timer = Timer::at(release_start + self.config.debounce);
-                    match select(self.driver.wait_for_press(), debounce_timer).await {
-                        Either::First(_) => {
-                            self.state = ButtonState::Pressed {
-                                start_time: press_start,
-                                count,
-                            };
-                        }
-                        Either::Second(_) => {
-                            self.state = ButtonState::WaitingForMultiClick {
-                                count,
-                                last_release: release_start,
-                            };
-                        }
-                    }
+            let pressed = self.fsm.is_pressed();
+            let deadline = self.fsm.deadline();
+            let driver = &mut self.driver;
+            let edge = async move {
+                if pressed {
+                    driver.wait_for_release().await
+                } else {
+                    driver.wait_for_press().await
                 }
-
-                ButtonState::WaitingForMultiClick {
-                    count,
-                    last_release,
-                } => {
-                    let multi_click_timer = Timer::at(last_release + self.config.multi_click_window);
-                    match select(self.driver.wait_for_press(), multi_click_timer).await {
-                        Either::First(_) => {
-                            self.state = ButtonState::PressDebouncing {
-                                count,
-                                start_time: Instant::now(),
-                            };
-                        }
-                        Either::Second(_) => {
-                            self.state = ButtonState::Idle;
-                           return match count {
-                                0 => {
-                                    // 如果 count 为 0 (来自长按释放)，则不产生事件，直接继续循环
-                                    continue;
-                                }
-                                1 => ButtonEvent::Click,
-                                2 => ButtonEvent::DoubleClick,
-                                n => ButtonEvent::MultipleClick { count: n },
-                            };
-                        }
-                    }
+            };
+            let timer = async move {
+                match deadline {
+                    Some(deadline) => Timer::at(deadline).await,
+                    None => pending().await,
+                }
+            };
+            let config_rx = &mut self.config_rx;
+            let config_change = async move {
+                match config_rx {
+                    Some(rx) => rx.changed().await,
+                    None => pending().await,
                 }
+            };
 
-                ButtonState::LongPress { start_time } => {
-                    // 计算下一次保持事件的时间点
-                    let next_hold_time = start_time + self.config.long_press_hold_interval;
-                    let hold_timer = Timer::at(next_hold_time);
-                    
-                    match select(self.driver.wait_for_release(), hold_timer).await {
-                        Either::First(_) => {
-                            self.state = ButtonState::Idle;
-                            return ButtonEvent::LongPressRelease;
-                        }
-                        Either::Second(_) => {
-                            // 更新开始时间为下一次保持事件的时间点
-                            self.state = ButtonState::LongPress {
-                                start_time: next_hold_time,
-                            };
-                            return ButtonEvent::LongPressHold;
-                        }
-                    }
+            // 电平变化只把状态机推进到它自己的时间戳，这样缓冲驱动中更早的
+            // 电平变化不会被已经到期的定时器抢先；只有定时器到期时才推进到当前时间。
+            let now = match select3(edge, timer, config_change).await {
+                Either3::First(at) => {
+                    let at = at?;
+                    self.fsm.on_edge(!pressed, at);
+                    at
                 }
+                Either3::Second(()) => Instant::now(),
+                Either3::Third(config) => {
+                    // 新配置立即作用于正在等待的定时器
+                    self.fsm.set_config(config);
+                    continue;
+                }
+            };
+            if let Some(event) = self.fsm.poll_timed(now) {
+                return Ok(event);
             }
         }
     }
 
     pub fn set_config(&mut self, new_config: ButtonConfig) {
-        self.config = new_config;
+        self.fsm.set_config(new_config);
     }
-    
+
+    /// 跟随共享配置。
+    ///
+    /// 如果 [`ConfigWatch`] 中已经有配置，会立即采用；之后每次发布的新配置都会在
+    /// 等待事件期间立即生效，包括正在计时的消抖、多击窗口和长按定时器。
+    /// 同一个 `ConfigWatch` 可以为一组按钮各创建一个接收端，统一调整它们的配置。
+    pub fn observe_config(&mut self, mut receiver: ConfigReceiver) {
+        if let Some(config) = receiver.try_get() {
+            self.fsm.set_config(config);
+        }
+        self.config_rx = Some(receiver);
+    }
+
     /// 获取底层驱动的不可变引用
     pub fn driver(&self) -> &T {
         &self.driver
@@ -196,9 +294,41 @@ impl<T: AsyncButtonDriver> Button<T> {
     pub fn driver_mut(&mut self) -> &mut T {
         &mut self.driver
     }
-    
+
+    /// 查询按钮当前所处的阶段，包括连击次数和已按住的时长。
+    ///
+    /// 只读取状态，不会消耗任何事件。
+    pub fn phase(&self) -> ButtonPhase {
+        self.fsm.phase(Instant::now())
+    }
+
+    /// 自适应消抖当前估计的抖动时长，详见 [`ButtonFsm::bounce_estimate`]。
+    pub fn bounce_estimate(&self) -> Option<Duration> {
+        self.fsm.bounce_estimate()
+    }
+
+    /// 当前的按键故障，详见 [`ButtonFsm::fault`]。
+    pub fn fault(&self) -> Option<ButtonFault> {
+        self.fsm.fault()
+    }
+
+    /// 使用统计的快照，详见 [`ButtonFsm::stats`]。
+    pub fn stats(&self) -> ButtonStats {
+        self.fsm.stats()
+    }
+
+    /// 清零使用统计。
+    pub fn reset_stats(&mut self) {
+        self.fsm.reset_stats();
+    }
+
+    /// 获取内部状态机的不可变引用
+    pub fn fsm(&self) -> &ButtonFsm {
+        &self.fsm
+    }
+
     /// 重置按钮状态到初始空闲状态
     pub fn reset(&mut self) {
-        self.state = ButtonState::Idle;
+        self.fsm.reset();
     }
-}
\ No newline at end of file
+}
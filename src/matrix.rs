@@ -1,27 +1,83 @@
+use core::{cell::RefCell, convert::Infallible, future::poll_fn, task::Poll};
+
 use embassy_sync::{
-    blocking_mutex::raw::CriticalSectionRawMutex,
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
     pubsub::{PubSubChannel, Publisher, Subscriber},
+    waitqueue::MultiWakerRegistration,
 };
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use embedded_hal::digital::{InputPin, OutputPin};
 
-use crate::AsyncButtonDriver;
+use crate::{AsyncButtonDriver, ERROR_BACKOFF};
 
 /// 表示矩阵键盘上的一个按键事件。
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeyEvent {
     pub row: u8,
     pub col: u8,
     pub pressed: bool,
+    /// 扫描任务读取到该状态变化的时间点。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::instant"))]
+    pub timestamp: Instant,
+}
+
+/// 矩阵扫描过程中的引脚错误。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixError<CE, RE> {
+    /// 驱动列引脚失败。
+    Column(CE),
+    /// 读取行引脚失败。
+    Row(RE),
+}
+
+/// 矩阵最多支持的行数与列数。
+pub const MAX_LINES: usize = 32;
+
+/// 扫描任务与各按键之间共享的通道。
+///
+/// 除了广播每一次按键状态变化，还保存所有按键最近一次扫描到的状态，
+/// 使任何时候创建的按键都能立即知道自己的当前状态。
+pub struct MatrixEventChannel<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> {
+    channel: PubSubChannel<CriticalSectionRawMutex, KeyEvent, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
+    state: Mutex<CriticalSectionRawMutex, RefCell<MatrixState<SUBS>>>,
+}
+
+/// 扫描任务最近一次扫描到的按键状态。
+struct MatrixState<const SUBS: usize> {
+    /// 每一列被按下按键的位掩码，第 n 位对应第 n 行。
+    pressed: [u32; MAX_LINES],
+    /// 是否已经完成第一次扫描。
+    scanned: bool,
+    /// 等待第一次扫描完成的按键。
+    wakers: MultiWakerRegistration<SUBS>,
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
+    MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    pub const fn new() -> Self {
+        Self {
+            channel: PubSubChannel::new(),
+            state: Mutex::new(RefCell::new(MatrixState {
+                pressed: [0; MAX_LINES],
+                scanned: false,
+                wakers: MultiWakerRegistration::new(),
+            })),
+        }
+    }
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> Default
+    for MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-pub type MatrixEventChannel<
-    'a,
-    const MSG_CAP: usize,
-    const SUBS: usize,
-    const SUBSCRIBER_CAP: usize,
-> = PubSubChannel<CriticalSectionRawMutex, KeyEvent, MSG_CAP, SUBS, SUBSCRIBER_CAP>;
 pub type MatrixEventSubscriber<
     'a,
     const MSG_CAP: usize,
@@ -42,19 +98,34 @@ pub struct MatrixButtonFactory<
     const SUBS: usize,
     const SUBSCRIBER_CAP: usize,
 > {
-    channel: &'a MatrixEventChannel<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
+    channel: &'a MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
 }
 
 impl<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
     MatrixButtonFactory<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>
 {
     /// 根据行列号创建一个新的矩阵按键实例。
+    ///
+    /// 可以在任何时候创建。如果扫描任务已经完成第一次扫描，新按键以最近一次扫描到的
+    /// 状态作为初始状态。
     pub fn button(&self, row: u8, col: u8) -> MatrixButton<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP> {
-        MatrixButton {
-            subscriber: self.channel.subscriber().unwrap(),
+        assert!(
+            (row as usize) < MAX_LINES && (col as usize) < MAX_LINES,
+            "Row and column must be less than MAX_LINES"
+        );
+        let mut button = MatrixButton {
+            channel: self.channel,
+            subscriber: self.channel.channel.subscriber().unwrap(),
             row,
             col,
-        }
+            pressed: false,
+            synced: false,
+            last_change: Instant::from_ticks(0),
+        };
+        // 先订阅再读取快照：扫描任务先更新快照再发布，
+        // 因此之后收到的消息要么已经反映在快照中，要么比它更新
+        button.sync_if_scanned();
+        button
     }
 }
 
@@ -70,8 +141,11 @@ pub struct MatrixDriver<
 > {
     cols: [C; COLS],
     rows: [R; ROWS],
+    channel: &'a MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     publisher: MatrixEventPublisher<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     last_states: [[bool; ROWS]; COLS],
+    /// 是否已经完成第一次扫描。
+    scanned: bool,
 }
 
 impl<
@@ -93,70 +167,190 @@ impl<
     /// 一个元组，包含:
     /// - `MatrixDriver`: 需要被 spawn 到后台任务中运行。
     /// - `MatrixButtonFactory`: 用于在程序中创建具体的按键实例。
+    ///
+    /// # Panics
+    /// 行数或列数超过 [`MAX_LINES`] 时 panic。
     pub fn new(
         cols: [C; COLS],
         rows: [R; ROWS],
         channel: &'a MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     ) -> (Self, MatrixButtonFactory<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>) {
+        assert!(
+            ROWS <= MAX_LINES && COLS <= MAX_LINES,
+            "Matrix must not have more than MAX_LINES rows or columns"
+        );
         let driver = Self {
             cols,
             rows,
-            publisher: channel.publisher().unwrap(),
+            channel,
+            publisher: channel.channel.publisher().unwrap(),
             last_states: [[false; ROWS]; COLS],
+            scanned: false,
         };
         let factory = MatrixButtonFactory { channel };
         (driver, factory)
     }
 
+    /// 运行扫描循环。引脚出错时等待 [`ERROR_BACKOFF`] 后重试。
     pub async fn run(mut self) -> ! {
+        loop {
+            if self.try_run().await.is_err() {
+                Timer::after(ERROR_BACKOFF).await;
+            }
+        }
+    }
+
+    /// 运行扫描循环，直到某个引脚返回错误。
+    ///
+    /// 出错后已知的按键状态保持不变，可以再次调用本方法继续扫描。
+    pub async fn try_run(&mut self) -> Result<Infallible, MatrixError<C::Error, R::Error>> {
         loop {
             for c in 0..COLS {
-                let _ = self.cols[c].set_low();
+                self.cols[c].set_low().map_err(MatrixError::Column)?;
                 Timer::after_micros(50).await;
 
-                for r in 0..ROWS {
-                    let is_pressed = self.rows[r].is_low().unwrap_or(false);
-                    if is_pressed != self.last_states[c][r] {
-                        self.last_states[c][r] = is_pressed;
-                        let event = KeyEvent {
-                            row: r as u8,
-                            col: c as u8,
-                            pressed: is_pressed,
-                        };
-                        self.publisher.publish(event).await;
-                    }
-                }
-                let _ = self.cols[c].set_high();
+                // 无论扫描是否出错，都先把列恢复为高电平
+                let scanned = self.scan_column(c).await;
+                self.cols[c].set_high().map_err(MatrixError::Column)?;
+                scanned?;
+            }
+            if !self.scanned {
+                self.scanned = true;
+                self.channel.state.lock(|state| {
+                    let mut state = state.borrow_mut();
+                    state.scanned = true;
+                    state.wakers.wake();
+                });
             }
             Timer::after(Duration::from_millis(5)).await;
         }
     }
+
+    async fn scan_column(&mut self, c: usize) -> Result<(), MatrixError<C::Error, R::Error>> {
+        for r in 0..ROWS {
+            let is_pressed = self.rows[r].is_low().map_err(MatrixError::Row)?;
+            if is_pressed != self.last_states[c][r] {
+                self.last_states[c][r] = is_pressed;
+                let event = KeyEvent {
+                    row: r as u8,
+                    col: c as u8,
+                    pressed: is_pressed,
+                    timestamp: Instant::now(),
+                };
+                trace!("matrix: publish {:?}", event);
+                self.channel.state.lock(|state| {
+                    let column = &mut state.borrow_mut().pressed[c];
+                    if is_pressed {
+                        *column |= 1 << r;
+                    } else {
+                        *column &= !(1 << r);
+                    }
+                });
+                self.publisher.publish(event).await;
+            }
+        }
+        Ok(())
+    }
 }
 
 pub struct MatrixButton<'a, const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> {
+    channel: &'a MatrixEventChannel<MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     subscriber: MatrixEventSubscriber<'a, MSG_CAP, SUBS, SUBSCRIBER_CAP>,
     row: u8,
     col: u8,
+    /// 最近一次收到的本按键状态。
+    pressed: bool,
+    /// 是否已经知道本按键的状态（收到过本按键的消息，或读取过扫描快照）。
+    synced: bool,
+    /// 最近一次状态变化的时间戳。
+    last_change: Instant,
+}
+
+impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize>
+    MatrixButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
+{
+    /// 记录一条消息，如果它属于本按键则返回 `true`。
+    fn observe(&mut self, event: KeyEvent) -> bool {
+        if event.row != self.row || event.col != self.col {
+            return false;
+        }
+        self.pressed = event.pressed;
+        self.synced = true;
+        self.last_change = event.timestamp;
+        true
+    }
+
+    /// 如果扫描任务已经完成第一次扫描，从快照中读取本按键的状态。
+    fn sync_if_scanned(&mut self) {
+        let pressed = self.channel.state.lock(|state| {
+            let state = state.borrow();
+            state
+                .scanned
+                .then(|| state.pressed[self.col as usize] & (1 << self.row) != 0)
+        });
+        if let Some(pressed) = pressed {
+            self.pressed = pressed;
+            self.synced = true;
+            self.last_change = Instant::now();
+        }
+    }
 }
 
 impl<const MSG_CAP: usize, const SUBS: usize, const SUBSCRIBER_CAP: usize> AsyncButtonDriver
     for MatrixButton<'_, MSG_CAP, SUBS, SUBSCRIBER_CAP>
 {
-    async fn wait_for_press(&mut self) {
+    type Error = Infallible;
+
+    async fn wait_for_press(&mut self) -> Result<Instant, Self::Error> {
+        if self.pressed {
+            return Ok(self.last_change);
+        }
         loop {
             let event = self.subscriber.next_message_pure().await;
-            if event.row == self.row && event.col == self.col && event.pressed {
-                return;
+            if self.observe(event) && event.pressed {
+                return Ok(event.timestamp);
             }
         }
     }
 
-    async fn wait_for_release(&mut self) {
+    async fn wait_for_release(&mut self) -> Result<Instant, Self::Error> {
+        if !self.pressed {
+            return Ok(self.last_change);
+        }
         loop {
             let event = self.subscriber.next_message_pure().await;
-            if event.row == self.row && event.col == self.col && !event.pressed {
-                return;
+            if self.observe(event) && !event.pressed {
+                return Ok(event.timestamp);
             }
         }
     }
+
+    /// 处理所有已到达的消息后，返回最近一次已知的状态。
+    ///
+    /// 只有在扫描任务还没有完成第一次扫描时才会等待，等到第一次扫描完成为止，
+    /// 因此上电时已被按住的按键不会被当作一次新的按下。
+    /// 已到达但尚未处理的按下与释放会被合并为最终状态。
+    async fn is_pressed(&mut self) -> Result<bool, Self::Error> {
+        if !self.synced {
+            poll_fn(|cx| {
+                self.channel.state.lock(|state| {
+                    let mut state = state.borrow_mut();
+                    if state.scanned {
+                        Poll::Ready(())
+                    } else {
+                        state.wakers.register(cx.waker());
+                        Poll::Pending
+                    }
+                })
+            })
+            .await;
+        }
+        while let Some(event) = self.subscriber.try_next_message_pure() {
+            self.observe(event);
+        }
+        if !self.synced {
+            self.sync_if_scanned();
+        }
+        Ok(self.pressed)
+    }
 }
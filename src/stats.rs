@@ -0,0 +1,109 @@
+use embassy_time::Duration;
+
+use crate::ButtonEvent;
+
+/// 按钮的使用与健康统计（需开启 [`ButtonConfig::collect_stats`](crate::ButtonConfig::collect_stats)）。
+///
+/// 所有计数在溢出时饱和。按下时长从消抖确认按下开始计算，
+/// 上电时就已被按住的那次按下不计入统计。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ButtonStats {
+    /// 消抖确认的按下次数。
+    pub presses: u32,
+    /// 报告的 `Click` 次数。
+    pub clicks: u32,
+    /// 报告的 `DoubleClick` 次数。
+    pub double_clicks: u32,
+    /// 报告的 `MultipleClick` 次数。
+    pub multiple_clicks: u32,
+    /// 报告的 `LongPressStart` 次数。
+    pub long_presses: u32,
+    /// 报告的 `ClickAndHold` 次数。
+    pub click_and_holds: u32,
+    /// 被消抖过滤掉的抖动次数。
+    pub bounces: u32,
+    /// 最长的一次按下。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub longest_press: Duration,
+    /// 累计按下时长。
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub total_pressed: Duration,
+}
+
+impl ButtonStats {
+    /// 所有计数为 0 的统计。
+    pub const fn new() -> Self {
+        Self {
+            presses: 0,
+            clicks: 0,
+            double_clicks: 0,
+            multiple_clicks: 0,
+            long_presses: 0,
+            click_and_holds: 0,
+            bounces: 0,
+            longest_press: Duration::from_ticks(0),
+            total_pressed: Duration::from_ticks(0),
+        }
+    }
+
+    pub(crate) fn record_press(&mut self) {
+        self.presses = self.presses.saturating_add(1);
+    }
+
+    pub(crate) fn record_release(&mut self, held: Duration) {
+        self.longest_press = self.longest_press.max(held);
+        self.total_pressed = Duration::from_ticks(
+            self.total_pressed
+                .as_ticks()
+                .saturating_add(held.as_ticks()),
+        );
+    }
+
+    pub(crate) fn record_bounce(&mut self) {
+        self.bounces = self.bounces.saturating_add(1);
+    }
+
+    /// 统计一个已报告的事件。取代先前事件的事件（乐观点击模式）会撤销被取代的那一次计数，
+    /// 因此每个手势只计一次。
+    pub(crate) fn record_event(&mut self, event: ButtonEvent, supersedes: bool) {
+        let superseded = match event {
+            ButtonEvent::Click => {
+                self.clicks = self.clicks.saturating_add(1);
+                return;
+            }
+            ButtonEvent::DoubleClick => {
+                self.double_clicks = self.double_clicks.saturating_add(1);
+                1
+            }
+            ButtonEvent::MultipleClick { count } => {
+                self.multiple_clicks = self.multiple_clicks.saturating_add(1);
+                count - 1
+            }
+            ButtonEvent::ClickAndHold { clicks } => {
+                self.click_and_holds = self.click_and_holds.saturating_add(1);
+                clicks
+            }
+            ButtonEvent::LongPressStart => {
+                self.long_presses = self.long_presses.saturating_add(1);
+                return;
+            }
+            _ => return,
+        };
+        if supersedes {
+            let counter = match superseded {
+                1 => &mut self.clicks,
+                2 => &mut self.double_clicks,
+                _ => &mut self.multiple_clicks,
+            };
+            *counter = counter.saturating_sub(1);
+        }
+    }
+}
+
+impl Default for ButtonStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
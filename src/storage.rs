@@ -0,0 +1,476 @@
+use embassy_time::Duration;
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+use crate::config::{
+    AdaptiveDebounce, ButtonConfig, ClickReporting, ConfigError, DebounceMode, FaultDetection,
+    Gestures, HoldAcceleration, HoldCatchUp, LongPressLevels, MAX_LONG_PRESS_LEVELS,
+};
+
+/// 一条记录最多可保存的配置数量。
+pub const MAX_CONFIGS: usize = 8;
+
+/// 当前的记录格式版本。格式发生不兼容的变化时递增，旧版本的记录会被拒绝。
+pub const RECORD_VERSION: u8 = 1;
+
+/// 每个配置编码后占用的字节数。
+pub const CONFIG_SIZE: usize = 79;
+
+const MAGIC: u16 = 0xB7C0;
+const HEADER_SIZE: usize = 4;
+const CRC_SIZE: usize = 4;
+/// `save` / `load` 使用的栈上缓冲区，留出余量以便按闪存的写入粒度补齐。
+const BUFFER_SIZE: usize = record_size(MAX_CONFIGS).next_multiple_of(256);
+
+/// 保存 `count` 个配置的记录占用的字节数（不含按写入粒度补齐的部分）。
+pub const fn record_size(count: usize) -> usize {
+    HEADER_SIZE + count * CONFIG_SIZE + CRC_SIZE
+}
+
+/// 记录本身无法编码或解码。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordError {
+    /// 配置数量为 0 或超过 [`MAX_CONFIGS`]。
+    InvalidCount,
+    /// 输出缓冲区放不下整条记录或记录中的所有配置。
+    BufferTooSmall,
+    /// 不是配置记录，例如闪存尚未写入过（已擦除）。
+    BadMagic,
+    /// 记录由不兼容的格式版本写入。
+    UnsupportedVersion(u8),
+    /// 校验和不匹配，记录已损坏或只写入了一部分。
+    CrcMismatch,
+    /// 校验和正确，但包含无法识别的字段值。
+    Corrupt,
+    /// 解码出的配置没有通过 [`ButtonConfig::validate`]。
+    InvalidConfig(ConfigError),
+    /// 配置中的时长超过 `u32::MAX` 微秒（约 71 分钟），无法保存。
+    DurationTooLong,
+}
+
+/// 读写闪存中的配置记录失败。
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageError<E> {
+    /// 闪存驱动返回的错误。
+    Flash(E),
+    /// 记录格式错误。
+    Record(RecordError),
+}
+
+impl<E> From<RecordError> for StorageError<E> {
+    fn from(error: RecordError) -> Self {
+        StorageError::Record(error)
+    }
+}
+
+/// 把一组配置编码为一条记录，返回写入 `buf` 的字节数。
+///
+/// 记录由 4 字节头部（魔数、格式版本、配置数量）、每个配置 [`CONFIG_SIZE`] 字节的数据
+/// 和覆盖前面所有字节的 CRC-32 组成，多字节整数均为小端序。时间参数以微秒保存，
+/// 包含超过 `u32::MAX` 微秒（约 71 分钟）的时长时返回 [`RecordError::DurationTooLong`]。
+pub fn encode(configs: &[ButtonConfig], buf: &mut [u8]) -> Result<usize, RecordError> {
+    if configs.is_empty() || configs.len() > MAX_CONFIGS {
+        return Err(RecordError::InvalidCount);
+    }
+    let len = record_size(configs.len());
+    if buf.len() < len {
+        return Err(RecordError::BufferTooSmall);
+    }
+
+    let mut w = Writer {
+        buf,
+        pos: 0,
+        too_long: false,
+    };
+    w.u16(MAGIC);
+    w.u8(RECORD_VERSION);
+    w.u8(configs.len() as u8);
+    for config in configs {
+        encode_config(&mut w, config);
+    }
+    if w.too_long {
+        return Err(RecordError::DurationTooLong);
+    }
+    let crc = crc32(&w.buf[..w.pos]);
+    w.u32(crc);
+    debug_assert_eq!(w.pos, len);
+    Ok(len)
+}
+
+/// 从 `bytes` 开头解码一条记录，把其中的配置写入 `out`，返回配置数量。
+///
+/// `bytes` 可以比记录更长（例如包含闪存中补齐的部分）。
+pub fn decode(bytes: &[u8], out: &mut [ButtonConfig]) -> Result<usize, RecordError> {
+    let count = decode_header(bytes)?;
+    if count > out.len() {
+        return Err(RecordError::BufferTooSmall);
+    }
+    let len = record_size(count);
+    if bytes.len() < len {
+        return Err(RecordError::CrcMismatch);
+    }
+    let body = len - CRC_SIZE;
+    let mut r = Reader { bytes, pos: body };
+    if r.u32() != crc32(&bytes[..body]) {
+        return Err(RecordError::CrcMismatch);
+    }
+
+    let mut r = Reader {
+        bytes,
+        pos: HEADER_SIZE,
+    };
+    for slot in &mut out[..count] {
+        let config = decode_config(&mut r).ok_or(RecordError::Corrupt)?;
+        config.validate().map_err(RecordError::InvalidConfig)?;
+        *slot = config;
+    }
+    Ok(count)
+}
+
+/// 把一组配置保存到闪存的 `offset` 处。
+///
+/// `offset` 必须与 `F::ERASE_SIZE` 对齐：记录所在的扇区会先被擦除，
+/// 记录末尾按 `F::WRITE_SIZE` 用 `0xFF` 补齐。
+pub fn save<F: NorFlash>(
+    flash: &mut F,
+    offset: u32,
+    configs: &[ButtonConfig],
+) -> Result<(), StorageError<F::Error>> {
+    let mut buf = [0xFF; BUFFER_SIZE];
+    let len = encode(configs, &mut buf)?;
+    let padded = len.next_multiple_of(F::WRITE_SIZE);
+    if padded > buf.len() {
+        return Err(RecordError::BufferTooSmall.into());
+    }
+    let erase_end = offset + len.next_multiple_of(F::ERASE_SIZE) as u32;
+    flash
+        .erase(offset, erase_end)
+        .map_err(StorageError::Flash)?;
+    flash
+        .write(offset, &buf[..padded])
+        .map_err(StorageError::Flash)
+}
+
+/// 从闪存的 `offset` 处读取由 [`save`] 保存的配置，返回读到的配置数量。
+///
+/// `out` 中超出配置数量的部分保持不变。
+pub fn load<F: ReadNorFlash>(
+    flash: &mut F,
+    offset: u32,
+    out: &mut [ButtonConfig],
+) -> Result<usize, StorageError<F::Error>> {
+    let mut buf = [0xFF; BUFFER_SIZE];
+    let header = HEADER_SIZE.next_multiple_of(F::READ_SIZE);
+    if header > buf.len() {
+        return Err(RecordError::BufferTooSmall.into());
+    }
+    flash
+        .read(offset, &mut buf[..header])
+        .map_err(StorageError::Flash)?;
+    let count = decode_header(&buf)?;
+
+    let len = record_size(count).next_multiple_of(F::READ_SIZE);
+    if len > buf.len() {
+        return Err(RecordError::BufferTooSmall.into());
+    }
+    flash
+        .read(offset, &mut buf[..len])
+        .map_err(StorageError::Flash)?;
+    Ok(decode(&buf[..len], out)?)
+}
+
+/// 与 [`load`] 相同，但读取失败时回退到默认配置。
+///
+/// `out` 中没有从闪存读到的配置都被设为 [`ButtonConfig::default`]，
+/// 返回从闪存读到的配置数量（记录不存在或已损坏时为 0）。
+pub fn load_or_default<F: ReadNorFlash>(
+    flash: &mut F,
+    offset: u32,
+    out: &mut [ButtonConfig],
+) -> usize {
+    let count = load(flash, offset, out).unwrap_or(0);
+    out[count..].fill(ButtonConfig::default());
+    count
+}
+
+fn decode_header(bytes: &[u8]) -> Result<usize, RecordError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(RecordError::BadMagic);
+    }
+    let mut r = Reader { bytes, pos: 0 };
+    if r.u16() != MAGIC {
+        return Err(RecordError::BadMagic);
+    }
+    let version = r.u8();
+    if version != RECORD_VERSION {
+        return Err(RecordError::UnsupportedVersion(version));
+    }
+    let count = r.u8() as usize;
+    if count == 0 || count > MAX_CONFIGS {
+        return Err(RecordError::InvalidCount);
+    }
+    Ok(count)
+}
+
+fn encode_config(w: &mut Writer, config: &ButtonConfig) {
+    w.duration(config.debounce);
+    w.duration(config.release_debounce);
+    w.u8(match config.debounce_mode {
+        DebounceMode::Deferred => 0,
+        DebounceMode::Eager => 1,
+        DebounceMode::Integrator => 2,
+    });
+    match config.adaptive_debounce {
+        Some(adaptive) => {
+            w.u8(1);
+            w.duration(adaptive.min);
+            w.duration(adaptive.max);
+        }
+        None => w.zeros(9),
+    }
+    w.duration(config.multi_click_window);
+    w.duration(config.long_press_time);
+    w.duration(config.long_press_hold_interval);
+    match config.long_press_hold_acceleration {
+        HoldAcceleration::None => w.zeros(11),
+        HoldAcceleration::Linear { every, step, min } => {
+            w.u8(1);
+            w.u16(every);
+            w.duration(step);
+            w.duration(min);
+        }
+        HoldAcceleration::Factor { percent, min } => {
+            w.u8(2);
+            w.u16(percent as u16);
+            w.u32(0);
+            w.duration(min);
+        }
+    }
+    w.u8(match config.hold_catch_up {
+        HoldCatchUp::Burst => 0,
+        HoldCatchUp::Skip => 1,
+        HoldCatchUp::Coalesce => 2,
+    });
+    let levels = config.long_press_levels.as_slice();
+    w.u8(levels.len() as u8);
+    for i in 0..MAX_LONG_PRESS_LEVELS {
+        w.duration(levels.get(i).copied().unwrap_or(Duration::from_ticks(0)));
+    }
+    match config.long_press_progress_interval {
+        Some(interval) => {
+            w.u8(1);
+            w.duration(interval);
+        }
+        None => w.zeros(5),
+    }
+    w.u8(match config.click_reporting {
+        ClickReporting::Deferred => 0,
+        ClickReporting::Optimistic => 1,
+    });
+    match config.fault_detection {
+        Some(fault) => {
+            w.u8(1);
+            w.duration(fault.stuck_after);
+            w.u8(fault.chatter_edges);
+            w.duration(fault.chatter_window);
+            w.u8(fault.suppress as u8);
+        }
+        None => w.zeros(11),
+    }
+    w.u8(config.gestures.bits());
+    w.u8(config.max_clicks);
+    w.u8(config.collect_stats as u8 | (config.raw_events as u8) << 1);
+}
+
+/// 解码一个配置，遇到无法识别的字段值时返回 `None`。
+fn decode_config(r: &mut Reader) -> Option<ButtonConfig> {
+    let debounce = r.duration();
+    let release_debounce = r.duration();
+    let debounce_mode = match r.u8() {
+        0 => DebounceMode::Deferred,
+        1 => DebounceMode::Eager,
+        2 => DebounceMode::Integrator,
+        _ => return None,
+    };
+    let adaptive_flag = r.u8();
+    let adaptive = AdaptiveDebounce {
+        min: r.duration(),
+        max: r.duration(),
+    };
+    let adaptive_debounce = flagged(adaptive_flag, adaptive)?;
+    let multi_click_window = r.duration();
+    let long_press_time = r.duration();
+    let long_press_hold_interval = r.duration();
+    let acceleration = r.u8();
+    let every = r.u16();
+    let step = r.duration();
+    let min = r.duration();
+    let long_press_hold_acceleration = match acceleration {
+        0 => HoldAcceleration::None,
+        1 => HoldAcceleration::Linear { every, step, min },
+        2 => HoldAcceleration::Factor {
+            percent: u8::try_from(every).ok()?,
+            min,
+        },
+        _ => return None,
+    };
+    let hold_catch_up = match r.u8() {
+        0 => HoldCatchUp::Burst,
+        1 => HoldCatchUp::Skip,
+        2 => HoldCatchUp::Coalesce,
+        _ => return None,
+    };
+    let level_count = r.u8() as usize;
+    let mut thresholds = [Duration::from_ticks(0); MAX_LONG_PRESS_LEVELS];
+    for threshold in &mut thresholds {
+        *threshold = r.duration();
+    }
+    let long_press_levels = LongPressLevels::new(thresholds.get(..level_count)?)?;
+    let progress_flag = r.u8();
+    let progress = r.duration();
+    let long_press_progress_interval = flagged(progress_flag, progress)?;
+    let click_reporting = match r.u8() {
+        0 => ClickReporting::Deferred,
+        1 => ClickReporting::Optimistic,
+        _ => return None,
+    };
+    let fault_flag = r.u8();
+    let stuck_after = r.duration();
+    let chatter_edges = r.u8();
+    let chatter_window = r.duration();
+    let suppress = r.bool()?;
+    let fault = FaultDetection {
+        stuck_after,
+        chatter_edges,
+        chatter_window,
+        suppress,
+    };
+    let fault_detection = flagged(fault_flag, fault)?;
+    let gestures = Gestures::from_bits(r.u8())?;
+    let max_clicks = r.u8();
+    let flags = r.u8();
+    if flags & !0b11 != 0 {
+        return None;
+    }
+
+    Some(ButtonConfig {
+        debounce,
+        release_debounce,
+        debounce_mode,
+        adaptive_debounce,
+        multi_click_window,
+        long_press_time,
+        long_press_hold_interval,
+        long_press_hold_acceleration,
+        hold_catch_up,
+        long_press_levels,
+        long_press_progress_interval,
+        click_reporting,
+        fault_detection,
+        gestures,
+        max_clicks,
+        collect_stats: flags & 0b01 != 0,
+        raw_events: flags & 0b10 != 0,
+    })
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    /// 是否遇到过放不进 `u32` 微秒的时长。
+    too_long: bool,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn zeros(&mut self, len: usize) {
+        self.buf[self.pos..self.pos + len].fill(0);
+        self.pos += len;
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn duration(&mut self, value: Duration) {
+        let micros = u32::try_from(value.as_micros()).unwrap_or_else(|_| {
+            self.too_long = true;
+            u32::MAX
+        });
+        self.u32(micros);
+    }
+}
+
+/// 调用方保证读取不会越过记录末尾。
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> [u8; N] {
+        let mut array = [0; N];
+        array.copy_from_slice(&self.bytes[self.pos..self.pos + N]);
+        self.pos += N;
+        array
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.array::<1>()[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.array())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.array())
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8() {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn duration(&mut self) -> Duration {
+        Duration::from_micros(self.u32() as u64)
+    }
+}
+
+/// 按标志字节解释可选字段：`Some(None)` 表示字段为空，`None` 表示标志无效。
+fn flagged<T>(flag: u8, value: T) -> Option<Option<T>> {
+    match flag {
+        0 => Some(None),
+        1 => Some(Some(value)),
+        _ => None,
+    }
+}
+
+/// CRC-32（IEEE 802.3，反射多项式 `0xEDB88320`）。
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,112 @@
+use core::{
+    borrow::BorrowMut,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::{AsyncButtonDriver, Button, ButtonEvent, TimedEvent};
+
+impl<T: AsyncButtonDriver> Button<T> {
+    /// 以 [`Stream`] 的形式借出按钮，每个元素对应一次 [`next_event`](Self::next_event)。
+    ///
+    /// 正在进行的等待保存在流中：`poll_next` 返回 `Pending` 之后，下一次轮询继续同一次等待，
+    /// 因此在 `select` 等组合子中丢弃 `next()` 的 future 不会丢失任何状态。
+    /// 手势识别的状态保存在按钮中，丢弃整个流之后再次创建流（或调用 `next_event`）
+    /// 同样会继续尚未完成的手势。
+    pub fn stream(&mut self) -> impl FusedStream<Item = ButtonEvent> + '_ {
+        Unfold::new(self, next_event)
+    }
+
+    /// 与 [`stream`](Self::stream) 相同，但返回附带时间信息的事件。
+    pub fn timed_stream(&mut self) -> impl FusedStream<Item = TimedEvent> + '_ {
+        Unfold::new(self, next_timed_event)
+    }
+
+    /// 与 [`stream`](Self::stream) 相同，但取得按钮的所有权，适合交给其他任务。
+    pub fn into_stream(self) -> impl FusedStream<Item = ButtonEvent> {
+        Unfold::new(self, next_event)
+    }
+}
+
+async fn next_event<T, B>(mut button: B) -> (ButtonEvent, B)
+where
+    T: AsyncButtonDriver,
+    B: BorrowMut<Button<T>>,
+{
+    let event = button.borrow_mut().next_event().await;
+    (event, button)
+}
+
+async fn next_timed_event<T, B>(mut button: B) -> (TimedEvent, B)
+where
+    T: AsyncButtonDriver,
+    B: BorrowMut<Button<T>>,
+{
+    let event = button.borrow_mut().next_timed_event().await;
+    (event, button)
+}
+
+pin_project! {
+    /// 用 `f` 反复把状态变为 `(元素, 新状态)` 的无限流。
+    ///
+    /// 两次产生元素之间，状态要么保存在 `state` 中，要么被正在进行的 `fut` 持有。
+    struct Unfold<S, F, Fut> {
+        state: Option<S>,
+        f: F,
+        #[pin]
+        fut: Option<Fut>,
+    }
+}
+
+impl<S, F, Fut> Unfold<S, F, Fut> {
+    fn new(state: S, f: F) -> Self {
+        Self {
+            state: Some(state),
+            f,
+            fut: None,
+        }
+    }
+}
+
+impl<S, F, Fut, Item> Stream for Unfold<S, F, Fut>
+where
+    F: FnMut(S) -> Fut,
+    Fut: Future<Output = (Item, S)>,
+{
+    type Item = Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Item>> {
+        let mut this = self.project();
+        if let Some(state) = this.state.take() {
+            this.fut.set(Some((this.f)(state)));
+        }
+        let fut = this
+            .fut
+            .as_mut()
+            .as_pin_mut()
+            .expect("state is held by the pending future");
+        let (item, state) = ready!(fut.poll(cx));
+        this.fut.set(None);
+        *this.state = Some(state);
+        Poll::Ready(Some(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<S, F, Fut, Item> FusedStream for Unfold<S, F, Fut>
+where
+    F: FnMut(S) -> Fut,
+    Fut: Future<Output = (Item, S)>,
+{
+    /// 按钮事件流永远不会结束。
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
@@ -1,10 +1,9 @@
 use core::convert::Infallible;
 use embassy_async_button::{
-    adc::{filter::RawFilter, AdcDriver, AsyncAdc},
+    adc::{filter::RawFilter, AdcChannel, AdcDriver, AsyncAdc},
     config::ButtonConfig,
     Button, ButtonEvent,
 };
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, pubsub::PubSubChannel};
 use embassy_time::{Duration, Timer};
 use tokio::sync::watch;
 
@@ -57,7 +56,7 @@ async fn test_adc_single_click() {
     let adc = MockAdc { receiver };
 
     // PubSubChannel 用于在 ADC 组和按钮驱动之间广播滤波后的 ADC 值
-    static CHANNEL: PubSubChannel<CriticalSectionRawMutex, u16, 4, 4, 4> = PubSubChannel::new();
+    static CHANNEL: AdcChannel<4, 4, 4> = AdcChannel::new();
     let config = ButtonConfig::default();
 
     // 1. 创建 ADC 按钮组，使用最简单的 RawFilter
@@ -88,3 +87,25 @@ async fn test_adc_single_click() {
     group_task.abort();
     simulator_task.abort();
 }
+
+// --- 故障 ADC ---
+
+struct FailingAdc;
+
+#[derive(Debug, PartialEq, Eq)]
+struct AdcFault;
+
+impl AsyncAdc for FailingAdc {
+    type Error = AdcFault;
+    async fn read(&mut self) -> Result<u16, Self::Error> {
+        Err(AdcFault)
+    }
+}
+
+#[tokio::test]
+async fn test_adc_read_error_is_surfaced() {
+    static CHANNEL: AdcChannel<4, 4, 4> = AdcChannel::new();
+    let (mut runner, _factory) = AdcDriver::new(FailingAdc, RawFilter, &CHANNEL);
+
+    assert_eq!(runner.try_run().await, Err(AdcFault));
+}
@@ -107,3 +107,38 @@ async fn test_keypad_button_integration() {
     group_task.abort();
     simulator_task.await.unwrap();
 }
+
+#[tokio::test]
+async fn test_keypad_button_created_after_first_decode() {
+    let (voltage_sender, adc_receiver) = watch::channel(0u16);
+    let adc = MockAdc {
+        receiver: adc_receiver,
+    };
+
+    static KEYMASK_CHANNEL: KeymaskChannel<4, 4, 4> = KeymaskChannel::new();
+    let (runner, factory) =
+        KeypadDriver::new(adc, RawFilter, MyTestKeypadDecoder, &KEYMASK_CHANNEL);
+    let group_task = tokio::spawn(runner.run());
+
+    // 解码任务发布了第一个掩码之后才创建按钮
+    voltage_sender.send(0).unwrap();
+    embassy_time::Timer::after(embassy_time::Duration::from_millis(50)).await;
+    let mut button = Button::new(factory.button(0), ButtonConfig::default());
+
+    let click = async {
+        embassy_time::Timer::after(embassy_time::Duration::from_millis(20)).await;
+        voltage_sender.send(1000).unwrap();
+        embassy_time::Timer::after(embassy_time::Duration::from_millis(60)).await;
+        voltage_sender.send(0).unwrap();
+    };
+    let validator = async {
+        embassy_time::with_timeout(embassy_time::Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到 Click 事件")
+    };
+    let ((), event) = tokio::join!(click, validator);
+
+    assert_eq!(event, ButtonEvent::Click);
+
+    group_task.abort();
+}
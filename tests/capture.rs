@@ -0,0 +1,87 @@
+mod common;
+
+use embassy_async_button::{
+    capture::{EdgeCapture, EdgeChannel},
+    config::ButtonConfig,
+    gpio::{ActiveLevel, GpioButton},
+    Button, ButtonEvent,
+};
+use embassy_time::{Duration, Timer};
+use tokio::sync::watch;
+
+use common::MockPin;
+
+// --- Test Harness (测试工具) ---
+
+/// 在消费者忙碌期间完成一次双击，随后再单击一次。
+async fn click_generator(tx: watch::Sender<bool>) {
+    Timer::after(Duration::from_millis(50)).await;
+    for _ in 0..2 {
+        tx.send(false).unwrap();
+        Timer::after(Duration::from_millis(50)).await;
+        tx.send(true).unwrap();
+        Timer::after(Duration::from_millis(100)).await;
+    }
+    Timer::after(Duration::from_millis(400)).await;
+    tx.send(false).unwrap();
+    Timer::after(Duration::from_millis(50)).await;
+    tx.send(true).unwrap();
+}
+
+#[tokio::test]
+async fn test_buffered_edges_survive_busy_consumer() {
+    let (tx, rx) = watch::channel(true);
+    let gpio_driver = GpioButton::new(MockPin { rx }, ActiveLevel::Low);
+
+    static EDGES: EdgeChannel<8> = EdgeChannel::new();
+    let (capture, buffered) = EdgeCapture::new(gpio_driver, &EDGES);
+    let mut button = Button::new(buffered, ButtonConfig::default());
+
+    let capture_task = tokio::spawn(capture.run());
+    let generator_task = tokio::spawn(click_generator(tx));
+
+    // 模拟一个忙碌的 UI 任务：双击发生时它根本没有在等待按钮事件
+    Timer::after(Duration::from_millis(600)).await;
+
+    for expected in [ButtonEvent::DoubleClick, ButtonEvent::Click] {
+        let event = embassy_time::with_timeout(Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到缓冲按钮事件");
+        assert_eq!(event, expected);
+    }
+
+    generator_task.await.unwrap();
+    capture_task.abort();
+}
+
+#[tokio::test]
+async fn test_buffered_button_reports_held_at_startup() {
+    // 上电时按键已被按住（低电平有效）
+    let (tx, rx) = watch::channel(false);
+    let gpio_driver = GpioButton::new(MockPin { rx }, ActiveLevel::Low);
+
+    static EDGES: EdgeChannel<8> = EdgeChannel::new();
+    let (capture, buffered) = EdgeCapture::new(gpio_driver, &EDGES);
+    let mut button = Button::new(buffered, ButtonConfig::default());
+
+    let capture_task = tokio::spawn(capture.run());
+    let release = async {
+        Timer::after(Duration::from_millis(100)).await;
+        tx.send(true).unwrap();
+    };
+    let validator = async {
+        embassy_time::with_timeout(Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到缓冲按钮事件")
+    };
+    let ((), event) = tokio::join!(release, validator);
+
+    match event {
+        ButtonEvent::HeldAtStartup { duration } => {
+            assert!(duration >= Duration::from_millis(90), "{duration:?}")
+        }
+        event => panic!("上电时按住的按键被当作新的按下：{event:?}"),
+    }
+
+    capture_task.abort();
+}
@@ -0,0 +1,47 @@
+//! 各集成测试共用的模拟硬件。
+
+use core::convert::Infallible;
+
+use tokio::sync::watch;
+
+/// 电平由 `watch` 通道控制的模拟 GPIO 引脚，`true` 表示高电平。
+pub struct MockPin {
+    pub rx: watch::Receiver<bool>,
+}
+
+impl embedded_hal::digital::ErrorType for MockPin {
+    type Error = Infallible;
+}
+impl embedded_hal::digital::InputPin for MockPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(*self.rx.borrow())
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!*self.rx.borrow())
+    }
+}
+impl embedded_hal_async::digital::Wait for MockPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.rx.wait_for(|state| *state).await.unwrap();
+        Ok(())
+    }
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.rx.wait_for(|state| !*state).await.unwrap();
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_low().await?;
+        self.wait_for_high().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_high().await?;
+        self.wait_for_low().await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        self.rx.wait_for(|_| true).await.unwrap();
+        Ok(())
+    }
+}
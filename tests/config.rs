@@ -0,0 +1,85 @@
+use embassy_async_button::config::{AdaptiveDebounce, ButtonConfig, ConfigError, LongPressLevels};
+use embassy_time::Duration;
+
+// 预设可以直接用于 static
+static PRESETS: [ButtonConfig; 4] = [
+    ButtonConfig::RESPONSIVE,
+    ButtonConfig::ACCESSIBLE,
+    ButtonConfig::GAMING,
+    ButtonConfig::INDUSTRIAL_GLOVE,
+];
+
+#[test]
+fn test_presets_are_valid() {
+    assert_eq!(ButtonConfig::DEFAULT.validate(), Ok(()));
+    assert_eq!(ButtonConfig::default(), ButtonConfig::DEFAULT);
+    for preset in &PRESETS {
+        assert_eq!(preset.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn test_builder_rejects_inconsistent_timings() {
+    let config = ButtonConfig::builder()
+        .long_press_time(Duration::from_millis(800))
+        .build()
+        .unwrap();
+    assert_eq!(config.long_press_time, Duration::from_millis(800));
+
+    assert_eq!(
+        ButtonConfig::builder()
+            .debounce(Duration::from_millis(600))
+            .multi_click_window(Duration::from_millis(600))
+            .build(),
+        Err(ConfigError::DebounceNotShorterThanLongPress)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .release_debounce(Duration::from_millis(500))
+            .build(),
+        Err(ConfigError::DebounceNotShorterThanLongPress)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .multi_click_window(Duration::from_millis(10))
+            .build(),
+        Err(ConfigError::MultiClickWindowShorterThanDebounce)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .release_debounce(Duration::from_millis(100))
+            .multi_click_window(Duration::from_millis(100))
+            .build(),
+        Err(ConfigError::MultiClickWindowNotLongerThanReleaseDebounce)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .long_press_hold_interval(Duration::from_ticks(0))
+            .build(),
+        Err(ConfigError::ZeroHoldInterval)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .long_press_levels(LongPressLevels::new(&[Duration::from_millis(500)]).unwrap())
+            .build(),
+        Err(ConfigError::LongPressLevelNotAfterLongPress)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .adaptive_debounce(Some(AdaptiveDebounce {
+                min: Duration::from_millis(30),
+                max: Duration::from_millis(10),
+            }))
+            .build(),
+        Err(ConfigError::InvalidAdaptiveDebounceRange)
+    );
+    assert_eq!(
+        ButtonConfig::builder()
+            .adaptive_debounce(Some(AdaptiveDebounce {
+                min: Duration::from_millis(10),
+                max: Duration::from_millis(500),
+            }))
+            .build(),
+        Err(ConfigError::AdaptiveDebounceNotShorterThanLongPress)
+    );
+}
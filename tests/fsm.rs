@@ -0,0 +1,939 @@
+use embassy_async_button::{
+    config::{
+        AdaptiveDebounce, ButtonConfig, ClickReporting, DebounceMode, FaultDetection, Gestures,
+        HoldAcceleration, HoldCatchUp, LongPressLevels,
+    },
+    ButtonEvent, ButtonFault, ButtonFsm, ButtonPhase, ButtonStats,
+};
+use embassy_time::{Duration, Instant};
+
+fn ms(t: u64) -> Instant {
+    Instant::from_millis(t)
+}
+
+/// 依次喂入带时间戳的电平变化，并在每一步之前把状态机推进到该时间点，
+/// 最后推进到 `end`，返回期间产生的所有事件。
+fn run(fsm: &mut ButtonFsm, edges: &[(u64, bool)], end: u64) -> Vec<ButtonEvent> {
+    let mut events = Vec::new();
+    for &(t, pressed) in edges {
+        while let Some(event) = fsm.poll(ms(t)) {
+            events.push(event);
+        }
+        fsm.on_edge(pressed, ms(t));
+    }
+    while let Some(event) = fsm.poll(ms(end)) {
+        events.push(event);
+    }
+    events
+}
+
+#[test]
+fn test_fsm_single_click() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    let events = run(&mut fsm, &[(100, true), (150, false)], 1000);
+
+    assert_eq!(events, [ButtonEvent::Click]);
+    assert_eq!(fsm.deadline(), None);
+}
+
+#[test]
+fn test_fsm_double_click() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    let events = run(
+        &mut fsm,
+        &[(100, true), (150, false), (250, true), (300, false)],
+        1000,
+    );
+
+    assert_eq!(events, [ButtonEvent::DoubleClick]);
+}
+
+#[test]
+fn test_fsm_bounce_is_rejected() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 短于消抖时间的脉冲不应产生任何事件
+    let events = run(&mut fsm, &[(100, true), (105, false)], 1000);
+
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_fsm_long_press_hold_and_release() {
+    let config = ButtonConfig::default();
+    let mut fsm = ButtonFsm::new(config);
+
+    // 按下后 20ms 完成消抖，520ms 开始长按，之后每 100ms 一次保持事件
+    fsm.on_edge(true, ms(0));
+    assert_eq!(fsm.poll(ms(519)), None);
+    assert_eq!(fsm.deadline(), Some(ms(520)));
+    assert_eq!(fsm.poll(ms(520)), Some(ButtonEvent::LongPressStart));
+    assert_eq!(fsm.deadline(), Some(ms(620)));
+    assert_eq!(
+        fsm.poll(ms(620)),
+        Some(ButtonEvent::LongPressHold { missed: 0 })
+    );
+    assert_eq!(fsm.poll(ms(650)), None);
+
+    fsm.on_edge(false, ms(650));
+    assert_eq!(
+        fsm.poll(ms(650)),
+        Some(ButtonEvent::LongPressRelease { level: 0 })
+    );
+    assert_eq!(fsm.poll(ms(2000)), None);
+}
+
+#[test]
+fn test_fsm_late_edge_processes_overdue_timers_first() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 调用方一直没有 poll，直到报告释放时才处理已经到期的长按定时器
+    fsm.on_edge(true, ms(0));
+    fsm.on_edge(false, ms(560));
+
+    assert_eq!(fsm.poll(ms(560)), Some(ButtonEvent::LongPressStart));
+    assert_eq!(
+        fsm.poll(ms(560)),
+        Some(ButtonEvent::LongPressRelease { level: 0 })
+    );
+    assert_eq!(fsm.poll(ms(560)), None);
+}
+
+#[test]
+fn test_fsm_very_late_release_is_not_dropped() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 积压的保持事件超过内部队列容量，释放在取出事件腾出空间后继续处理
+    fsm.on_edge(true, ms(0));
+    fsm.on_edge(false, ms(2000));
+    assert!(!fsm.is_pressed());
+
+    let mut events = Vec::new();
+    while let Some(event) = fsm.poll_timed(ms(2000)) {
+        events.push(event);
+    }
+    let kinds: Vec<_> = events.iter().map(|timed| timed.event).collect();
+    let mut expected = vec![ButtonEvent::LongPressStart];
+    expected.extend([ButtonEvent::LongPressHold { missed: 0 }; 14]);
+    expected.push(ButtonEvent::LongPressRelease { level: 0 });
+    assert_eq!(kinds, expected);
+    assert_eq!(events[14].at, ms(1920));
+    assert_eq!(events[15].at, ms(2000));
+    assert_eq!(fsm.deadline(), None);
+    assert_eq!(fsm.phase(ms(2000)), ButtonPhase::Idle);
+}
+
+#[test]
+fn test_fsm_long_press_levels() {
+    let levels =
+        LongPressLevels::new(&[Duration::from_millis(1000), Duration::from_millis(3000)]).unwrap();
+    let config = ButtonConfig {
+        long_press_hold_interval: Duration::from_millis(1000),
+        long_press_levels: levels,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // 等级阈值从消抖确认（20ms）开始计算，与保持事件同时到期时先报告等级
+    let events = run(&mut fsm, &[(0, true), (3100, false)], 4000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::LongPressStart,
+            ButtonEvent::LongPressLevel { level: 1 },
+            ButtonEvent::LongPressHold { missed: 0 },
+            ButtonEvent::LongPressHold { missed: 0 },
+            ButtonEvent::LongPressLevel { level: 2 },
+            ButtonEvent::LongPressRelease { level: 2 },
+        ]
+    );
+
+    // 只越过第一级就释放
+    let events = run(&mut fsm, &[(5000, true), (6500, false)], 7000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::LongPressStart,
+            ButtonEvent::LongPressLevel { level: 1 },
+            ButtonEvent::LongPressRelease { level: 1 },
+        ]
+    );
+
+    // 未经检查的配置中早于长按阈值的等级与 LongPressStart 同时报告，时间不会倒退
+    let config = ButtonConfig {
+        long_press_levels: LongPressLevels::new(&[
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        ])
+        .unwrap(),
+        ..ButtonConfig::default()
+    };
+    assert!(config.validate().is_err());
+    let mut fsm = ButtonFsm::new(config);
+    fsm.on_edge(true, ms(0));
+    let mut timed = Vec::new();
+    while let Some(event) = fsm.poll_timed(ms(560)) {
+        timed.push((event.event, event.at));
+    }
+    assert_eq!(
+        timed,
+        [
+            (ButtonEvent::LongPressStart, ms(520)),
+            (ButtonEvent::LongPressLevel { level: 1 }, ms(520)),
+            (ButtonEvent::LongPressLevel { level: 2 }, ms(520)),
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_long_press_progress() {
+    let config = ButtonConfig {
+        long_press_progress_interval: Some(Duration::from_millis(200)),
+        long_press_time: Duration::from_millis(1000),
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // 进度从消抖确认（20ms）开始计算，达到阈值时产生 LongPressStart
+    let events = run(&mut fsm, &[(0, true), (1100, false)], 2000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::LongPressProgress { permille: 200 },
+            ButtonEvent::LongPressProgress { permille: 400 },
+            ButtonEvent::LongPressProgress { permille: 600 },
+            ButtonEvent::LongPressProgress { permille: 800 },
+            ButtonEvent::LongPressStart,
+            ButtonEvent::LongPressRelease { level: 0 },
+        ]
+    );
+
+    // 提前释放产生 LongPressCancelled 而不是 Click，也不再等待多击
+    fsm.on_edge(true, ms(3000));
+    assert_eq!(
+        fsm.poll(ms(3220)),
+        Some(ButtonEvent::LongPressProgress { permille: 200 })
+    );
+    fsm.on_edge(false, ms(3300));
+    let cancelled = fsm.poll_timed(ms(3320)).unwrap();
+    assert_eq!(cancelled.event, ButtonEvent::LongPressCancelled);
+    assert_eq!(cancelled.press_duration, Duration::from_millis(280));
+    assert_eq!(fsm.deadline(), None);
+}
+
+#[test]
+fn test_fsm_click_and_hold() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 双击后按住：之前的点击不单独报告，保持与释放事件携带相同的点击次数
+    let events = run(
+        &mut fsm,
+        &[
+            (0, true),
+            (50, false),
+            (150, true),
+            (200, false),
+            (300, true),
+            (950, false),
+        ],
+        2000,
+    );
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::ClickAndHold { clicks: 2 },
+            ButtonEvent::ClickAndHoldRepeat {
+                clicks: 2,
+                missed: 0
+            },
+            ButtonEvent::ClickAndHoldRelease { clicks: 2 },
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_gesture_mask_and_max_clicks() {
+    // 关闭多击后，单击在释放确认（170ms）时立即产生
+    let config = ButtonConfig {
+        gestures: Gestures::ALL.without(Gestures::MULTI_CLICK),
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    fsm.on_edge(true, ms(100));
+    fsm.on_edge(false, ms(150));
+    assert_eq!(fsm.poll(ms(170)), Some(ButtonEvent::Click));
+    assert_eq!(fsm.deadline(), None);
+
+    // 达到 max_clicks 时立即产生 MultipleClick，之后的按下重新计数
+    let config = ButtonConfig {
+        max_clicks: 3,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    let edges = [
+        (0, true),
+        (50, false),
+        (100, true),
+        (150, false),
+        (200, true),
+        (250, false),
+    ];
+    let events = run(&mut fsm, &edges, 270);
+    assert_eq!(events, [ButtonEvent::MultipleClick { count: 3 }]);
+    assert_eq!(fsm.deadline(), None);
+
+    // 关闭长按后，按住再久也按点击处理
+    let config = ButtonConfig {
+        gestures: Gestures::CLICK,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    let events = run(
+        &mut fsm,
+        &[(0, true), (2000, false), (2100, true), (2150, false)],
+        3000,
+    );
+    assert_eq!(events, [ButtonEvent::Click, ButtonEvent::Click]);
+
+    // 关闭长按后也不报告长按进度，释放不会被当作取消
+    let config = ButtonConfig {
+        gestures: Gestures::CLICK,
+        long_press_progress_interval: Some(Duration::from_millis(100)),
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    let events = run(&mut fsm, &[(0, true), (3000, false)], 4000);
+    assert_eq!(events, [ButtonEvent::Click]);
+}
+
+#[test]
+fn test_fsm_optimistic_clicks() {
+    let config = ButtonConfig {
+        click_reporting: ClickReporting::Optimistic,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // 第一次释放确认后立即报告 Click，第二次报告取代它的 DoubleClick
+    fsm.on_edge(true, ms(0));
+    fsm.on_edge(false, ms(50));
+    let click = fsm.poll_timed(ms(70)).unwrap();
+    assert_eq!((click.event, click.supersedes), (ButtonEvent::Click, false));
+    fsm.on_edge(true, ms(150));
+    fsm.on_edge(false, ms(200));
+    let double = fsm.poll_timed(ms(220)).unwrap();
+    assert_eq!(
+        (double.event, double.supersedes),
+        (ButtonEvent::DoubleClick, true)
+    );
+
+    // 窗口结束时不再重复报告
+    assert_eq!(fsm.poll(ms(1000)), None);
+
+    // 单击后按住：ClickAndHold 取代已经报告的 Click
+    fsm.on_edge(true, ms(2000));
+    fsm.on_edge(false, ms(2050));
+    fsm.on_edge(true, ms(2150));
+    let mut timed = Vec::new();
+    while let Some(event) = fsm.poll_timed(ms(2700)) {
+        timed.push((event.event, event.supersedes));
+    }
+    assert_eq!(
+        timed,
+        [
+            (ButtonEvent::Click, false),
+            (ButtonEvent::ClickAndHold { clicks: 1 }, true),
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_hold_catch_up() {
+    /// 长按开始后（520ms）消费者停顿到 1000ms 才再次 poll。
+    fn stalled(hold_catch_up: HoldCatchUp) -> Vec<(ButtonEvent, Instant, u16)> {
+        let config = ButtonConfig {
+            hold_catch_up,
+            ..ButtonConfig::default()
+        };
+        let mut fsm = ButtonFsm::new(config);
+        fsm.on_edge(true, ms(0));
+        assert_eq!(fsm.poll(ms(520)), Some(ButtonEvent::LongPressStart));
+        let mut timed = Vec::new();
+        while let Some(event) = fsm.poll_timed(ms(1000)) {
+            timed.push((event.event, event.at, event.repeat));
+        }
+        // 之后按正常周期继续
+        timed.extend(fsm.poll_timed(ms(1020)).map(|e| (e.event, e.at, e.repeat)));
+        timed
+    }
+
+    assert_eq!(
+        stalled(HoldCatchUp::Burst),
+        [
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(620), 1),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(720), 2),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(820), 3),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(920), 4),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(1020), 5),
+        ]
+    );
+    // 跳过时从 1000ms 重新计时，下一次保持事件在 1100ms
+    assert_eq!(
+        stalled(HoldCatchUp::Skip),
+        [(ButtonEvent::LongPressHold { missed: 0 }, ms(1000), 4)]
+    );
+    assert_eq!(
+        stalled(HoldCatchUp::Coalesce),
+        [
+            (ButtonEvent::LongPressHold { missed: 3 }, ms(920), 4),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(1020), 5),
+        ]
+    );
+    let mut fsm = ButtonFsm::new(ButtonConfig {
+        hold_catch_up: HoldCatchUp::Skip,
+        ..ButtonConfig::default()
+    });
+    fsm.on_edge(true, ms(0));
+    assert_eq!(fsm.poll(ms(520)), Some(ButtonEvent::LongPressStart));
+    assert!(fsm.poll(ms(1000)).is_some());
+    assert_eq!(fsm.deadline(), Some(ms(1100)));
+}
+
+#[test]
+fn test_fsm_hold_catch_up_after_long_stall() {
+    /// 长按开始后（520ms）消费者停顿到 `until` 才再次 poll。
+    fn stalled(config: ButtonConfig, until: u64) -> Vec<(ButtonEvent, Instant)> {
+        let mut fsm = ButtonFsm::new(config);
+        fsm.on_edge(true, ms(0));
+        assert_eq!(fsm.poll(ms(520)), Some(ButtonEvent::LongPressStart));
+        let mut timed = Vec::new();
+        while let Some(event) = fsm.poll_timed(ms(until)) {
+            timed.push((event.event, event.at));
+        }
+        timed
+    }
+
+    // 停顿远超 u16::MAX 个周期时，合并次数封顶而不是溢出，其余的由下一次事件报告
+    let config = ButtonConfig {
+        long_press_hold_interval: Duration::from_millis(1),
+        hold_catch_up: HoldCatchUp::Coalesce,
+        ..ButtonConfig::default()
+    };
+    let first = 521 + u16::MAX as u64;
+    assert_eq!(
+        stalled(config, 100_000),
+        [
+            (ButtonEvent::LongPressHold { missed: u16::MAX }, ms(first)),
+            (
+                ButtonEvent::LongPressHold {
+                    missed: (100_000 - first - 1) as u16
+                },
+                ms(100_000)
+            ),
+        ]
+    );
+    let config = ButtonConfig {
+        hold_catch_up: HoldCatchUp::Skip,
+        ..config
+    };
+    assert_eq!(
+        stalled(config, 100_000),
+        [(ButtonEvent::LongPressHold { missed: 0 }, ms(100_000))]
+    );
+
+    // 加速曲线到达下限前后，合并结果与逐个补发一致
+    let config = ButtonConfig {
+        long_press_hold_interval: Duration::from_millis(64),
+        long_press_hold_acceleration: HoldAcceleration::Factor {
+            percent: 50,
+            min: Duration::from_millis(1),
+        },
+        hold_catch_up: HoldCatchUp::Burst,
+        ..ButtonConfig::default()
+    };
+    let burst = stalled(config, 5_000);
+    let coalesced = stalled(
+        ButtonConfig {
+            hold_catch_up: HoldCatchUp::Coalesce,
+            ..config
+        },
+        5_000,
+    );
+    assert_eq!(
+        coalesced,
+        [(
+            ButtonEvent::LongPressHold {
+                missed: burst.len() as u16 - 1
+            },
+            burst.last().unwrap().1
+        )]
+    );
+}
+
+#[test]
+fn test_fsm_hold_acceleration() {
+    // 每两次保持事件周期缩短 40ms，最短 20ms
+    let config = ButtonConfig {
+        long_press_hold_acceleration: HoldAcceleration::Linear {
+            every: 2,
+            step: Duration::from_millis(40),
+            min: Duration::from_millis(20),
+        },
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    fsm.on_edge(true, ms(0));
+    assert_eq!(fsm.poll(ms(520)), Some(ButtonEvent::LongPressStart));
+    let mut timed = Vec::new();
+    while let Some(event) = fsm.poll_timed(ms(900)) {
+        timed.push((event.at, event.repeat));
+    }
+    assert_eq!(
+        timed,
+        [
+            (ms(620), 1),
+            (ms(720), 2),
+            (ms(780), 3),
+            (ms(840), 4),
+            (ms(860), 5),
+            (ms(880), 6),
+            (ms(900), 7),
+        ]
+    );
+
+    // 按比例加速
+    let curve = HoldAcceleration::Factor {
+        percent: 50,
+        min: Duration::from_millis(10),
+    };
+    let initial = Duration::from_millis(100);
+    let intervals: Vec<_> = (0..5)
+        .map(|holds| curve.interval(initial, holds).as_millis())
+        .collect();
+    assert_eq!(intervals, [100, 50, 25, 12, 10]);
+}
+
+#[test]
+fn test_fsm_zero_hold_interval_is_floored() {
+    // 未经检查的零周期配置按一个 tick 处理，而不是在同一时刻无限产生保持事件
+    let config = ButtonConfig {
+        long_press_hold_interval: Duration::from_ticks(0),
+        ..ButtonConfig::default()
+    };
+    assert!(config.validate().is_err());
+    let mut fsm = ButtonFsm::new(config);
+
+    let events = run(&mut fsm, &[(0, true), (521, false)], 1000);
+
+    let holds = Duration::from_millis(1).as_ticks() as usize;
+    assert_eq!(events.len(), holds + 2);
+    assert_eq!(events[0], ButtonEvent::LongPressStart);
+    assert_eq!(events[1], ButtonEvent::LongPressHold { missed: 0 });
+    assert_eq!(
+        events.last(),
+        Some(&ButtonEvent::LongPressRelease { level: 0 })
+    );
+}
+
+/// 与 `run` 相同，但返回事件及其时间点。
+fn run_timed(fsm: &mut ButtonFsm, edges: &[(u64, bool)], end: u64) -> Vec<(ButtonEvent, u64)> {
+    let mut events = Vec::new();
+    for &(t, pressed) in edges {
+        while let Some(timed) = fsm.poll_timed(ms(t)) {
+            events.push((timed.event, timed.at.as_millis()));
+        }
+        fsm.on_edge(pressed, ms(t));
+    }
+    while let Some(timed) = fsm.poll_timed(ms(end)) {
+        events.push((timed.event, timed.at.as_millis()));
+    }
+    events
+}
+
+#[test]
+fn test_fsm_eager_debounce() {
+    let config = ButtonConfig {
+        debounce_mode: DebounceMode::Eager,
+        release_debounce: Duration::from_millis(30),
+        raw_events: true,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // 第一个电平变化立即确认，锁定期间的抖动被忽略
+    let edges = [
+        (100, true),
+        (105, false),
+        (108, true),
+        (150, false),
+        (155, true),
+        (160, false),
+    ];
+    assert_eq!(
+        run_timed(&mut fsm, &edges, 1000),
+        [
+            (ButtonEvent::Down, 100),
+            (ButtonEvent::Up, 150),
+            (ButtonEvent::Click, 400),
+        ]
+    );
+
+    // 锁定期间松开，锁定结束时补报释放
+    assert_eq!(
+        run_timed(&mut fsm, &[(2000, true), (2010, false)], 3000),
+        [
+            (ButtonEvent::Down, 2000),
+            (ButtonEvent::Up, 2020),
+            (ButtonEvent::Click, 2270),
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_integrator_debounce() {
+    let config = ButtonConfig {
+        debounce_mode: DebounceMode::Integrator,
+        release_debounce: Duration::from_millis(30),
+        raw_events: true,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // 抖动只扣除其持续时间：按下累计 5 - 3 = 2ms，再保持 18ms 后确认；
+    // 释放累计 10 - 2 = 8ms，再保持 22ms 后确认
+    let edges = [
+        (0, true),
+        (5, false),
+        (8, true),
+        (100, false),
+        (110, true),
+        (112, false),
+    ];
+    assert_eq!(
+        run_timed(&mut fsm, &edges, 1000),
+        [
+            (ButtonEvent::Down, 26),
+            (ButtonEvent::Up, 134),
+            (ButtonEvent::Click, 350),
+        ]
+    );
+
+    // 抖动时间超过累计时长时放弃这次按下
+    assert!(run(&mut fsm, &[(2000, true), (2005, false)], 3000).is_empty());
+}
+
+#[test]
+fn test_fsm_adaptive_debounce() {
+    assert_eq!(
+        ButtonFsm::new(ButtonConfig::default()).bounce_estimate(),
+        None
+    );
+
+    let config = ButtonConfig {
+        adaptive_debounce: Some(AdaptiveDebounce {
+            min: Duration::from_millis(4),
+            max: Duration::from_millis(40),
+        }),
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+    // 从上限开始：估计值为上限的一半
+    assert_eq!(fsm.bounce_estimate(), Some(Duration::from_millis(20)));
+
+    // 没有抖动的按键让估计值逐渐回落，消抖时间降到下限
+    let mut t = 0;
+    for _ in 0..30 {
+        let events = run(&mut fsm, &[(t, true), (t + 100, false)], t + 1000);
+        assert_eq!(events, [ButtonEvent::Click]);
+        t += 1000;
+    }
+    assert!(fsm.bounce_estimate().unwrap() < Duration::from_millis(2));
+
+    // 一串持续 5ms 的抖动被立即采纳（在下一次电平变化时结算）
+    let edges = [(t, true), (t + 3, false), (t + 5, true), (t + 100, false)];
+    let events = run(&mut fsm, &edges, t + 1000);
+    assert_eq!(events, [ButtonEvent::Click]);
+    assert_eq!(fsm.bounce_estimate(), Some(Duration::from_millis(5)));
+}
+
+fn fault_config(suppress: bool) -> ButtonConfig {
+    ButtonConfig {
+        fault_detection: Some(FaultDetection {
+            stuck_after: Duration::from_secs(5),
+            chatter_edges: 6,
+            chatter_window: Duration::from_millis(100),
+            suppress,
+        }),
+        ..ButtonConfig::default()
+    }
+}
+
+#[test]
+fn test_fsm_stuck_fault() {
+    // 不抑制时，故障事件与手势事件一起报告
+    let mut fsm = ButtonFsm::new(fault_config(false));
+    let events = run(&mut fsm, &[(0, true), (6000, false)], 7000);
+    assert_eq!(events.first(), Some(&ButtonEvent::LongPressStart));
+    assert!(events.contains(&ButtonEvent::Fault(ButtonFault::Stuck)));
+    assert_eq!(
+        events[events.len() - 2..],
+        [
+            ButtonEvent::LongPressRelease { level: 0 },
+            ButtonEvent::FaultCleared
+        ]
+    );
+
+    // 抑制时，故障期间只有故障事件，以及故障之前已经开始的长按的释放
+    let mut fsm = ButtonFsm::new(fault_config(true));
+    fsm.on_edge(true, ms(0));
+    let mut events = Vec::new();
+    while let Some(event) = fsm.poll(ms(4999)) {
+        events.push(event);
+    }
+    assert_eq!(events.first(), Some(&ButtonEvent::LongPressStart));
+    assert_eq!(
+        fsm.poll(ms(5000)),
+        Some(ButtonEvent::Fault(ButtonFault::Stuck))
+    );
+    assert_eq!(fsm.fault(), Some(ButtonFault::Stuck));
+    assert_eq!(fsm.poll(ms(6000)), None);
+    let events = run(&mut fsm, &[(6000, false)], 7000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::LongPressRelease { level: 0 },
+            ButtonEvent::FaultCleared
+        ]
+    );
+    assert_eq!(fsm.fault(), None);
+
+    // 故障之后才开始的长按被整体丢弃，不会只报告释放
+    let mut config = fault_config(true);
+    if let Some(fault) = config.fault_detection.as_mut() {
+        fault.stuck_after = Duration::from_millis(300);
+    }
+    let mut fsm = ButtonFsm::new(config);
+    let events = run(&mut fsm, &[(0, true), (1000, false)], 2000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::Fault(ButtonFault::Stuck),
+            ButtonEvent::FaultCleared
+        ]
+    );
+
+    // 恢复后手势识别照常进行
+    let events = run(&mut fsm, &[(8000, true), (8050, false)], 9000);
+    assert_eq!(events, [ButtonEvent::Click]);
+}
+
+#[test]
+fn test_fsm_chattering_fault() {
+    let mut fsm = ButtonFsm::new(fault_config(true));
+
+    // 100ms 内 6 次电平变化判定为抖动故障，期间的点击被丢弃
+    let edges = [
+        (0, true),
+        (30, false),
+        (40, true),
+        (70, false),
+        (80, true),
+        (95, false),
+        (150, true),
+        (180, false),
+    ];
+    let events = run_timed(&mut fsm, &edges, 1000);
+    // 释放后 100ms 没有电平变化才恢复
+    assert_eq!(
+        events,
+        [
+            (ButtonEvent::Fault(ButtonFault::Chattering), 95),
+            (ButtonEvent::FaultCleared, 280),
+        ]
+    );
+
+    let events = run(&mut fsm, &[(2000, true), (2050, false)], 3000);
+    assert_eq!(events, [ButtonEvent::Click]);
+}
+
+#[test]
+fn test_fsm_stats() {
+    let config = ButtonConfig {
+        collect_stats: true,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    let edges = [
+        // 带一次抖动的单击，按下 28ms..100ms
+        (0, true),
+        (5, false),
+        (8, true),
+        (100, false),
+        // 双击，每次按下 30ms
+        (1000, true),
+        (1050, false),
+        (1150, true),
+        (1200, false),
+        // 长按 980ms
+        (2000, true),
+        (3000, false),
+    ];
+    run(&mut fsm, &edges, 4000);
+
+    assert_eq!(
+        fsm.stats(),
+        ButtonStats {
+            presses: 4,
+            clicks: 1,
+            double_clicks: 1,
+            long_presses: 1,
+            bounces: 1,
+            longest_press: Duration::from_millis(980),
+            total_pressed: Duration::from_millis(1112),
+            ..ButtonStats::new()
+        }
+    );
+
+    fsm.reset_stats();
+    assert_eq!(fsm.stats(), ButtonStats::new());
+}
+
+#[test]
+fn test_fsm_held_at_startup() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 上电时已被按住：按住期间不产生长按，释放时报告按住时长
+    fsm.on_startup(true, ms(0));
+    assert_eq!(fsm.poll(ms(3000)), None);
+    fsm.on_edge(false, ms(3000));
+    assert_eq!(
+        fsm.poll(ms(3000)),
+        Some(ButtonEvent::HeldAtStartup {
+            duration: Duration::from_millis(3000)
+        })
+    );
+
+    // 之后的按下按正常流程识别
+    let events = run(&mut fsm, &[(3100, true), (3150, false)], 4000);
+    assert_eq!(events, [ButtonEvent::Click]);
+}
+
+#[test]
+fn test_fsm_raw_down_up_events() {
+    let config = ButtonConfig {
+        raw_events: true,
+        ..ButtonConfig::default()
+    };
+    let mut fsm = ButtonFsm::new(config);
+
+    // Down/Up 在消抖确认时立即产生，不影响双击识别
+    fsm.on_edge(true, ms(100));
+    assert_eq!(fsm.poll(ms(120)), Some(ButtonEvent::Down));
+    fsm.on_edge(false, ms(150));
+    assert_eq!(fsm.poll(ms(170)), Some(ButtonEvent::Up));
+    let events = run(&mut fsm, &[(250, true), (300, false)], 1000);
+    assert_eq!(
+        events,
+        [ButtonEvent::Down, ButtonEvent::Up, ButtonEvent::DoubleClick]
+    );
+
+    // 长按同样会报告按下与释放
+    let events = run(&mut fsm, &[(2000, true), (2600, false)], 3000);
+    assert_eq!(
+        events,
+        [
+            ButtonEvent::Down,
+            ButtonEvent::LongPressStart,
+            ButtonEvent::Up,
+            ButtonEvent::LongPressRelease { level: 0 },
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_timed_events() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+
+    // 单击：报告最后一次按下的时长（从 20ms 消抖确认到 150ms 释放）
+    fsm.on_edge(true, ms(0));
+    fsm.on_edge(false, ms(150));
+    let click = fsm.poll_timed(ms(1000)).unwrap();
+    assert_eq!(click.event, ButtonEvent::Click);
+    assert_eq!(click.at, ms(400));
+    assert_eq!(click.press_duration, Duration::from_millis(130));
+    assert_eq!(click.repeat, 0);
+
+    // 长按：保持事件带序号，释放事件报告总按住时长和保持次数
+    fsm.on_edge(true, ms(2000));
+    let mut timed = Vec::new();
+    while let Some(event) = fsm.poll_timed(ms(2740)) {
+        timed.push(event);
+    }
+    fsm.on_edge(false, ms(2740));
+    timed.extend(fsm.poll_timed(ms(2740)));
+
+    let summary: Vec<_> = timed
+        .iter()
+        .map(|t| (t.event, t.at, t.press_duration.as_millis(), t.repeat))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (ButtonEvent::LongPressStart, ms(2520), 500, 0),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(2620), 600, 1),
+            (ButtonEvent::LongPressHold { missed: 0 }, ms(2720), 700, 2),
+            (ButtonEvent::LongPressRelease { level: 0 }, ms(2740), 720, 2),
+        ]
+    );
+}
+
+#[test]
+fn test_fsm_phase() {
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+    assert_eq!(fsm.phase(ms(0)), ButtonPhase::Idle);
+
+    // 消抖期间仍视为空闲，确认后进入按住阶段
+    fsm.on_edge(true, ms(100));
+    assert_eq!(fsm.phase(ms(110)), ButtonPhase::Idle);
+    assert_eq!(fsm.poll(ms(150)), None);
+    assert_eq!(
+        fsm.phase(ms(150)),
+        ButtonPhase::Pressed {
+            clicks: 1,
+            held: Duration::from_millis(30)
+        }
+    );
+    assert!(fsm.phase(ms(150)).is_pressed());
+
+    // 释放后在多击窗口内等待
+    fsm.on_edge(false, ms(200));
+    assert_eq!(fsm.poll(ms(230)), None);
+    assert_eq!(
+        fsm.phase(ms(230)),
+        ButtonPhase::WaitingForMultiClick { clicks: 1 }
+    );
+
+    // 第二次按住直到单击后按住
+    fsm.on_edge(true, ms(300));
+    assert_eq!(fsm.poll(ms(330)), None);
+    assert_eq!(
+        fsm.phase(ms(330)),
+        ButtonPhase::Pressed {
+            clicks: 2,
+            held: Duration::from_millis(10)
+        }
+    );
+    assert_eq!(
+        fsm.poll(ms(820)),
+        Some(ButtonEvent::ClickAndHold { clicks: 1 })
+    );
+    assert_eq!(
+        fsm.phase(ms(900)),
+        ButtonPhase::LongPress {
+            held: Duration::from_millis(580)
+        }
+    );
+}
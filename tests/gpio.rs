@@ -1,15 +1,15 @@
+mod common;
+
 use embassy_async_button::{
-    config::ButtonConfig,
+    config::{ButtonConfig, ConfigWatch},
     gpio::{ActiveLevel, GpioButton},
     Button, ButtonEvent,
 };
-use core::convert::Infallible;
 use embassy_time::{Duration, Timer};
 use tokio::sync::watch;
 
-struct MockPin {
-    rx: watch::Receiver<bool>,
-}
+use common::MockPin;
+
 struct MockPinController {
     tx: watch::Sender<bool>,
 }
@@ -19,42 +19,6 @@ impl MockPin {
         (MockPinController { tx }, Self { rx })
     }
 }
-impl embedded_hal::digital::ErrorType for MockPin {
-    type Error = Infallible;
-}
-impl embedded_hal::digital::InputPin for MockPin {
-    fn is_high(&mut self) -> Result<bool, Self::Error> {
-        Ok(*self.rx.borrow())
-    }
-    fn is_low(&mut self) -> Result<bool, Self::Error> {
-        Ok(!*self.rx.borrow())
-    }
-}
-impl embedded_hal_async::digital::Wait for MockPin {
-    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
-        self.rx.wait_for(|state| *state).await.unwrap();
-        Ok(())
-    }
-    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
-        self.rx.wait_for(|state| !*state).await.unwrap();
-        Ok(())
-    }
-
-    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
-        self.wait_for_low().await?;
-        self.wait_for_high().await
-    }
-
-    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
-        self.wait_for_high().await?;
-        self.wait_for_low().await
-    }
-
-    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
-        self.rx.wait_for(|_| true).await.unwrap();
-        Ok(())
-    }
-}
 
 // 1. 事件生成器
 async fn event_generator(controller: MockPinController) {
@@ -92,8 +56,8 @@ async fn event_validator(mut button: Button<GpioButton<MockPin>>) {
         ButtonEvent::Click,
         ButtonEvent::DoubleClick,
         ButtonEvent::LongPressStart,
-        ButtonEvent::LongPressHold,
-        ButtonEvent::LongPressRelease,
+        ButtonEvent::LongPressHold { missed: 0 },
+        ButtonEvent::LongPressRelease { level: 0 },
     ];
 
     for expected in expected_events {
@@ -168,4 +132,149 @@ async fn test_gpio_triple_click_event() {
         triple_click_event_generator(controller),
         triple_click_event_validator(button)
     );
-}
\ No newline at end of file
+}
+
+// --- 故障引脚 ---
+
+/// 一个每次等待都立即失败的引脚，模拟损坏的 GPIO 外设。
+struct FailingPin;
+
+#[derive(Debug, PartialEq, Eq)]
+struct PinFault;
+
+impl embedded_hal::digital::Error for PinFault {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+impl embedded_hal::digital::ErrorType for FailingPin {
+    type Error = PinFault;
+}
+impl embedded_hal::digital::InputPin for FailingPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Err(PinFault)
+    }
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Err(PinFault)
+    }
+}
+impl embedded_hal_async::digital::Wait for FailingPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        Err(PinFault)
+    }
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Err(PinFault)
+    }
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Err(PinFault)
+    }
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Err(PinFault)
+    }
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Err(PinFault)
+    }
+}
+
+#[tokio::test]
+async fn test_gpio_pin_error_is_surfaced_not_clicked() {
+    let gpio_driver = GpioButton::new(FailingPin, ActiveLevel::Low);
+    let mut button = Button::new(gpio_driver, ButtonConfig::default());
+
+    // try_next_event 把错误交给调用方
+    assert_eq!(button.try_next_event().await, Err(PinFault));
+
+    // next_event 退避重试，既不忙等，也不会报告虚假的点击
+    let result = embassy_time::with_timeout(Duration::from_millis(300), button.next_event()).await;
+    assert!(result.is_err(), "故障引脚不应产生事件: {result:?}");
+}
+
+// --- 上电时按住 ---
+
+#[tokio::test]
+async fn test_gpio_held_at_startup() {
+    // 引脚在按钮创建时就已是低电平（按下）
+    let (tx, rx) = watch::channel(false);
+    let gpio_driver = GpioButton::new(MockPin { rx }, ActiveLevel::Low);
+    let mut button = Button::new(gpio_driver, ButtonConfig::default());
+
+    let generator = async {
+        Timer::after(Duration::from_millis(800)).await;
+        tx.send(true).unwrap();
+        Timer::after(Duration::from_millis(100)).await;
+        tx.send(false).unwrap();
+        Timer::after(Duration::from_millis(50)).await;
+        tx.send(true).unwrap();
+    };
+
+    let validator = async {
+        let event = embassy_time::with_timeout(Duration::from_secs(2), button.next_event())
+            .await
+            .expect("测试超时，未等到上电按住事件");
+        match event {
+            ButtonEvent::HeldAtStartup { duration } => {
+                assert!(duration >= Duration::from_millis(750), "{duration:?}");
+            }
+            other => panic!("期望 HeldAtStartup，实际为 {other:?}"),
+        }
+
+        let event = embassy_time::with_timeout(Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到单击事件");
+        assert_eq!(event, ButtonEvent::Click);
+    };
+
+    tokio::join!(generator, validator);
+}
+
+// --- 共享配置 ---
+
+#[tokio::test]
+async fn test_gpio_shared_config_applies_mid_wait() {
+    static CONFIG: ConfigWatch<2> = ConfigWatch::new();
+    let slow = ButtonConfig {
+        long_press_time: Duration::from_secs(10),
+        ..ButtonConfig::default()
+    };
+    let fast = ButtonConfig {
+        long_press_time: Duration::from_millis(500),
+        ..ButtonConfig::default()
+    };
+    CONFIG.sender().send(slow);
+
+    // 同一个共享配置驱动一组按钮，创建时立即采用当前配置
+    let (controller, driver_pin) = MockPin::split();
+    let mut button = Button::new(
+        GpioButton::new(driver_pin, ActiveLevel::Low),
+        ButtonConfig::default(),
+    );
+    button.observe_config(CONFIG.dyn_receiver().unwrap());
+    let (_other_controller, other_pin) = MockPin::split();
+    let mut other = Button::new(
+        GpioButton::new(other_pin, ActiveLevel::Low),
+        ButtonConfig::default(),
+    );
+    other.observe_config(CONFIG.dyn_receiver().unwrap());
+    assert_eq!(button.fsm().config(), &slow);
+    assert_eq!(other.fsm().config(), &slow);
+
+    // 按钮正在等待 10s 的长按定时器时，设置任务缩短长按阈值
+    let settings = async {
+        Timer::after(Duration::from_millis(50)).await;
+        controller.tx.send(false).unwrap();
+        Timer::after(Duration::from_millis(300)).await;
+        CONFIG.sender().send(fast);
+    };
+    let validator = async {
+        let event = embassy_time::with_timeout(Duration::from_secs(2), button.next_event())
+            .await
+            .expect("新配置没有在等待期间生效");
+        assert_eq!(event, ButtonEvent::LongPressStart);
+    };
+    tokio::join!(settings, validator);
+
+    // 组内其他按钮在下一次等待事件时同样采用新配置
+    let idle = embassy_time::with_timeout(Duration::from_millis(50), other.next_event()).await;
+    assert!(idle.is_err());
+    assert_eq!(other.fsm().config(), &fast);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use embassy_async_button::{
+    capture::{Edge, EdgeCapture, EdgeChannel},
+    config::ButtonConfig,
+    gpio::{ActiveLevel, GpioButton},
+    Button, ButtonEvent, HubEvent, InputHub,
+};
+use embassy_time::{Duration, Instant, Timer};
+use tokio::sync::watch;
+
+use common::MockPin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Busy,
+    Buffered,
+}
+
+#[tokio::test]
+async fn test_hub_busy_button_does_not_starve_others() {
+    // Busy: 一直按住且保持周期为 1ms 的 GPIO 按钮，事件总是来不及处理
+    let (busy_tx, busy_rx) = watch::channel(true);
+    let busy = Button::new(
+        GpioButton::new(MockPin { rx: busy_rx }, ActiveLevel::Low),
+        ButtonConfig::builder()
+            .long_press_time(Duration::from_millis(30))
+            .long_press_hold_interval(Duration::from_millis(1))
+            .build()
+            .unwrap(),
+    );
+
+    // Buffered: 直接向缓冲通道写入电平变化的另一种驱动
+    let (_idle_tx, idle_rx) = watch::channel(true);
+    let channel = EdgeChannel::<8>::new();
+    let (_capture, buffered) = EdgeCapture::new(
+        GpioButton::new(MockPin { rx: idle_rx }, ActiveLevel::Low),
+        &channel,
+    );
+    let buffered = Button::new(buffered, ButtonConfig::default());
+
+    let mut hub = InputHub::new(((Input::Busy, busy), (Input::Buffered, buffered)));
+
+    let generator = async {
+        // 与 EdgeCapture 一样，先写入初始状态
+        channel
+            .send(Edge {
+                pressed: false,
+                timestamp: Instant::now(),
+            })
+            .await;
+        Timer::after(Duration::from_millis(10)).await;
+        busy_tx.send(false).unwrap();
+        Timer::after(Duration::from_millis(100)).await;
+        for pressed in [true, false] {
+            channel
+                .send(Edge {
+                    pressed,
+                    timestamp: Instant::now(),
+                })
+                .await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    };
+
+    let validator = async {
+        let mut events: Vec<HubEvent<Input>> = Vec::new();
+        // Busy 的保持事件总是就绪，没有公平轮转时 Buffered 的事件永远取不出来
+        let click = loop {
+            assert!(events.len() < 500, "缓冲按钮的事件被饿死");
+            let event = hub.next_event().await;
+            events.push(event);
+            if event.id == Input::Buffered {
+                break event;
+            }
+            // 消费者处理得比保持事件产生得慢
+            Timer::after(Duration::from_millis(3)).await;
+        };
+
+        assert_eq!(click.event.event, ButtonEvent::Click);
+        // 事件的时间信息完整保留：按下时长从消抖确认（20ms）开始计算
+        assert!(click.event.press_duration >= Duration::from_millis(30));
+        assert!(!click.event.supersedes);
+        assert!(events.len() > 20, "{}", events.len());
+        assert!(events
+            .iter()
+            .any(|e| e.id == Input::Busy && e.event.event == ButtonEvent::LongPressStart));
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(event.seq, i as u32);
+        }
+    };
+    tokio::join!(generator, validator);
+}
@@ -1,10 +1,9 @@
 use core::convert::Infallible;
 use embassy_async_button::{
     config::ButtonConfig,
-    matrix::{KeyEvent, MatrixDriver},
+    matrix::{MatrixDriver, MatrixEventChannel},
     Button, ButtonEvent,
 };
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, pubsub::PubSubChannel};
 use embassy_time::{Duration, Timer};
 use std::sync::{Arc, Mutex}; // 【新】引入 Arc 和 std::sync::Mutex
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
@@ -67,9 +66,13 @@ async fn matrix_simulator(
         *pin_state.lock().unwrap() = false;
     }
 
-    // 等待扫描循环激活我们想要按下的那一列
+    // 等待扫描循环第二次激活我们想要按下的那一列，使第一次扫描时按键仍未按下
+    let mut activations = 0;
     while let Some(active_col) = col_receiver.recv().await {
         if active_col == key_to_press.1 {
+            activations += 1;
+        }
+        if activations == 2 {
             // 就是这一列！现在模拟“按下”状态
             *rows_state[key_to_press.0 as usize].lock().unwrap() = true;
             break;
@@ -115,8 +118,7 @@ async fn test_matrix_single_click() {
     ];
 
     // --- 设置 async-button ---
-    static CHANNEL: PubSubChannel<CriticalSectionRawMutex, KeyEvent, 4, 4, 4> =
-        PubSubChannel::new();
+    static CHANNEL: MatrixEventChannel<4, 4, 4> = MatrixEventChannel::new();
     let config = ButtonConfig::default();
 
     // 1. 创建矩阵键盘组
@@ -144,3 +146,78 @@ async fn test_matrix_single_click() {
     group_task.abort();
     simulator_task.abort();
 }
+
+#[tokio::test]
+async fn test_matrix_key_held_at_startup() {
+    let (col_sender, _col_receiver) = mpsc::unbounded_channel();
+    // 上电时第 1 行的按键已被按住
+    let rows_state: [Arc<Mutex<bool>>; 2] =
+        [Arc::new(Mutex::new(false)), Arc::new(Mutex::new(true))];
+    let cols = [0, 1].map(|col_index| MockOutputPin {
+        col_index,
+        sender: col_sender.clone(),
+    });
+    let rows = rows_state.clone().map(|state| MockInputPin { state });
+
+    static CHANNEL: MatrixEventChannel<4, 4, 4> = MatrixEventChannel::new();
+    let (runner, factory) = MatrixDriver::new(cols, rows, &CHANNEL);
+    let mut button = Button::new(factory.button(1, 1), ButtonConfig::default());
+
+    // 按钮在第一次扫描之前开始等待，扫描结果表明按键已被按住
+    let group_task = tokio::spawn(runner.run());
+    let release = async {
+        Timer::after(Duration::from_millis(100)).await;
+        *rows_state[1].lock().unwrap() = false;
+    };
+    let validator = async {
+        embassy_time::with_timeout(Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到矩阵按钮事件")
+    };
+    let ((), event) = tokio::join!(release, validator);
+
+    match event {
+        ButtonEvent::HeldAtStartup { duration } => {
+            assert!(duration >= Duration::from_millis(90), "{duration:?}")
+        }
+        event => panic!("上电时按住的按键被当作新的按下：{event:?}"),
+    }
+
+    group_task.abort();
+}
+
+#[tokio::test]
+async fn test_matrix_button_created_after_first_scan() {
+    let (col_sender, _col_receiver) = mpsc::unbounded_channel();
+    let rows_state: [Arc<Mutex<bool>>; 2] = std::array::from_fn(|_| Arc::new(Mutex::new(false)));
+    let cols = [0, 1].map(|col_index| MockOutputPin {
+        col_index,
+        sender: col_sender.clone(),
+    });
+    let rows = rows_state.clone().map(|state| MockInputPin { state });
+
+    static CHANNEL: MatrixEventChannel<4, 4, 4> = MatrixEventChannel::new();
+    let (runner, factory) = MatrixDriver::new(cols, rows, &CHANNEL);
+    let group_task = tokio::spawn(runner.run());
+
+    // 扫描任务已经运行了若干轮之后才创建按钮
+    Timer::after(Duration::from_millis(50)).await;
+    let mut button = Button::new(factory.button(1, 1), ButtonConfig::default());
+
+    let click = async {
+        Timer::after(Duration::from_millis(20)).await;
+        *rows_state[1].lock().unwrap() = true;
+        Timer::after(Duration::from_millis(60)).await;
+        *rows_state[1].lock().unwrap() = false;
+    };
+    let validator = async {
+        embassy_time::with_timeout(Duration::from_secs(1), button.next_event())
+            .await
+            .expect("测试超时，未等到矩阵按钮事件")
+    };
+    let ((), event) = tokio::join!(click, validator);
+
+    assert_eq!(event, ButtonEvent::Click);
+
+    group_task.abort();
+}
@@ -0,0 +1,199 @@
+use embassy_async_button::{
+    adc_keypad::Keymask,
+    config::{
+        AdaptiveDebounce, ButtonConfig, ClickReporting, DebounceMode, FaultDetection, Gestures,
+        HoldAcceleration, HoldCatchUp, LongPressLevels,
+    },
+    matrix::KeyEvent,
+    ButtonEvent, ButtonFault, ButtonPhase, ButtonStats, TimedEvent,
+};
+use embassy_time::{Duration, Instant};
+use serde::{de::DeserializeOwned, Serialize};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + core::fmt::Debug>(value: T) {
+    let mut buf = [0; 256];
+    let bytes = postcard::to_slice(&value, &mut buf).unwrap();
+    assert_eq!(postcard::from_bytes::<T>(bytes).unwrap(), value);
+}
+
+#[test]
+fn test_serde_config_round_trip() {
+    let tuned = ButtonConfig::builder()
+        .release_debounce(ms(25))
+        .debounce_mode(DebounceMode::Integrator)
+        .adaptive_debounce(Some(AdaptiveDebounce {
+            min: ms(2),
+            max: ms(30),
+        }))
+        .long_press_hold_acceleration(HoldAcceleration::Linear {
+            every: 3,
+            step: ms(20),
+            min: ms(50),
+        })
+        .hold_catch_up(HoldCatchUp::Coalesce)
+        .long_press_levels(LongPressLevels::new(&[ms(2000), ms(5000)]).unwrap())
+        .long_press_progress_interval(Some(ms(100)))
+        .click_reporting(ClickReporting::Optimistic)
+        .fault_detection(Some(FaultDetection {
+            stuck_after: Duration::from_secs(30),
+            chatter_edges: 12,
+            chatter_window: ms(200),
+            suppress: true,
+        }))
+        .gestures(Gestures::ALL.without(Gestures::CLICK_AND_HOLD))
+        .max_clicks(3)
+        .collect_stats(true)
+        .raw_events(true)
+        .build()
+        .unwrap();
+
+    round_trip(tuned);
+    round_trip(ButtonConfig::DEFAULT);
+    round_trip(ButtonConfig::RESPONSIVE);
+    round_trip(ButtonConfig::ACCESSIBLE);
+    round_trip(ButtonConfig::GAMING);
+    round_trip(ButtonConfig::INDUSTRIAL_GLOVE);
+    round_trip(HoldAcceleration::Factor {
+        percent: 80,
+        min: ms(40),
+    });
+}
+
+#[test]
+fn test_serde_event_round_trip() {
+    let events = [
+        ButtonEvent::Down,
+        ButtonEvent::Up,
+        ButtonEvent::Click,
+        ButtonEvent::DoubleClick,
+        ButtonEvent::MultipleClick { count: 5 },
+        ButtonEvent::LongPressProgress { permille: 750 },
+        ButtonEvent::LongPressCancelled,
+        ButtonEvent::LongPressStart,
+        ButtonEvent::LongPressHold { missed: 2 },
+        ButtonEvent::LongPressLevel { level: 1 },
+        ButtonEvent::LongPressRelease { level: 3 },
+        ButtonEvent::ClickAndHold { clicks: 1 },
+        ButtonEvent::ClickAndHoldRepeat {
+            clicks: 2,
+            missed: 0,
+        },
+        ButtonEvent::ClickAndHoldRelease { clicks: 1 },
+        ButtonEvent::Fault(ButtonFault::Stuck),
+        ButtonEvent::Fault(ButtonFault::Chattering),
+        ButtonEvent::FaultCleared,
+        ButtonEvent::HeldAtStartup { duration: ms(1500) },
+    ];
+    for event in events {
+        round_trip(event);
+    }
+
+    round_trip(TimedEvent {
+        event: ButtonEvent::DoubleClick,
+        at: Instant::from_millis(123_456),
+        press_duration: ms(80),
+        repeat: 0,
+        supersedes: true,
+    });
+    round_trip(KeyEvent {
+        row: 2,
+        col: 3,
+        pressed: true,
+        timestamp: Instant::from_millis(42),
+    });
+    round_trip(Keymask {
+        mask: 0b1010,
+        timestamp: Instant::from_millis(7),
+    });
+}
+
+#[test]
+fn test_serde_stats_and_phase_round_trip() {
+    round_trip(ButtonStats::new());
+    round_trip(ButtonStats {
+        presses: 120,
+        clicks: 90,
+        double_clicks: 12,
+        multiple_clicks: 2,
+        long_presses: 5,
+        click_and_holds: 1,
+        bounces: 37,
+        longest_press: Duration::from_secs(4),
+        total_pressed: Duration::from_secs(95),
+    });
+
+    let phases = [
+        ButtonPhase::Idle,
+        ButtonPhase::Pressed {
+            clicks: 2,
+            held: ms(120),
+        },
+        ButtonPhase::WaitingForMultiClick { clicks: 1 },
+        ButtonPhase::LongPress { held: ms(1500) },
+        ButtonPhase::HeldAtStartup { held: ms(30) },
+    ];
+    for phase in phases {
+        round_trip(phase);
+    }
+}
+
+#[test]
+fn test_serde_time_is_encoded_as_microseconds() {
+    // postcard 的 u64 为 LEB128 变长整数：2000 = [0xD0, 0x0F]，30000 = [0xB0, 0xEA, 0x01]
+    let mut buf = [0; 16];
+    let adaptive = AdaptiveDebounce {
+        min: ms(2),
+        max: ms(30),
+    };
+    assert_eq!(
+        postcard::to_slice(&adaptive, &mut buf).unwrap(),
+        [0xD0, 0x0F, 0xB0, 0xEA, 0x01]
+    );
+
+    let event = ButtonEvent::HeldAtStartup { duration: ms(2) };
+    let bytes = postcard::to_slice(&event, &mut buf).unwrap();
+    assert_eq!(&bytes[1..], [0xD0, 0x0F]);
+
+    let key = KeyEvent {
+        row: 0,
+        col: 1,
+        pressed: false,
+        timestamp: Instant::from_millis(2),
+    };
+    assert_eq!(
+        postcard::to_slice(&key, &mut buf).unwrap(),
+        [0, 1, 0, 0xD0, 0x0F]
+    );
+}
+
+#[test]
+fn test_serde_rejects_invalid_invariants() {
+    let mut buf = [0; 32];
+
+    let bytes = postcard::to_slice(&0x10u8, &mut buf).unwrap();
+    assert!(postcard::from_bytes::<Gestures>(bytes).is_err());
+
+    let bytes = postcard::to_slice(&[5000u64, 2000][..], &mut buf).unwrap();
+    assert!(postcard::from_bytes::<LongPressLevels>(bytes).is_err());
+
+    let bytes = postcard::to_slice(&[1u64, 2, 3, 4, 5][..], &mut buf).unwrap();
+    assert!(postcard::from_bytes::<LongPressLevels>(bytes).is_err());
+
+    let mut big = [0; 256];
+    let invalid = ButtonConfig {
+        long_press_hold_interval: Duration::from_ticks(0),
+        ..ButtonConfig::default()
+    };
+    let bytes = postcard::to_slice(&invalid, &mut big).unwrap();
+    assert!(postcard::from_bytes::<ButtonConfig>(bytes).is_err());
+
+    let bytes = postcard::to_slice(&[1000u64, 3000][..], &mut buf).unwrap();
+    assert_eq!(
+        postcard::from_bytes::<LongPressLevels>(bytes).unwrap(),
+        LongPressLevels::new(&[ms(1), ms(3)]).unwrap()
+    );
+}
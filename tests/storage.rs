@@ -0,0 +1,275 @@
+use embassy_async_button::{
+    config::{
+        AdaptiveDebounce, ButtonConfig, ClickReporting, DebounceMode, FaultDetection, Gestures,
+        HoldAcceleration, HoldCatchUp, LongPressLevels,
+    },
+    storage::{self, RecordError, StorageError},
+};
+use embassy_time::Duration;
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+
+const SECTOR: usize = 256;
+
+/// 内存中的 NOR 闪存：擦除后为 0xFF，写入只能把位从 1 变为 0。
+struct MockFlash {
+    data: [u8; 4 * SECTOR],
+    erases: usize,
+}
+
+impl MockFlash {
+    fn new() -> Self {
+        Self {
+            data: [0xFF; 4 * SECTOR],
+            erases: 0,
+        }
+    }
+}
+
+impl ErrorType for MockFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for MockFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for MockFlash {
+    const WRITE_SIZE: usize = 8;
+    const ERASE_SIZE: usize = SECTOR;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        self.data[from as usize..to as usize].fill(0xFF);
+        self.erases += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        for (cell, byte) in self.data[offset as usize..].iter_mut().zip(bytes) {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// 每个字段都偏离默认值的配置。
+fn tuned() -> ButtonConfig {
+    ButtonConfig::builder()
+        .debounce(ms(15))
+        .release_debounce(ms(25))
+        .debounce_mode(DebounceMode::Integrator)
+        .adaptive_debounce(Some(AdaptiveDebounce {
+            min: ms(2),
+            max: ms(30),
+        }))
+        .multi_click_window(ms(450))
+        .long_press_time(ms(900))
+        .long_press_hold_interval(ms(250))
+        .long_press_hold_acceleration(HoldAcceleration::Linear {
+            every: 3,
+            step: ms(20),
+            min: ms(50),
+        })
+        .hold_catch_up(HoldCatchUp::Coalesce)
+        .long_press_levels(LongPressLevels::new(&[ms(2000), ms(5000)]).unwrap())
+        .long_press_progress_interval(Some(ms(100)))
+        .click_reporting(ClickReporting::Optimistic)
+        .fault_detection(Some(FaultDetection {
+            stuck_after: Duration::from_secs(30),
+            chatter_edges: 12,
+            chatter_window: ms(200),
+            suppress: true,
+        }))
+        .gestures(Gestures::ALL.without(Gestures::CLICK_AND_HOLD))
+        .max_clicks(3)
+        .collect_stats(true)
+        .raw_events(true)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_storage_round_trips_several_configs() {
+    let configs = [
+        tuned(),
+        ButtonConfig::GAMING,
+        ButtonConfig::ACCESSIBLE,
+        ButtonConfig {
+            long_press_hold_acceleration: HoldAcceleration::Factor {
+                percent: 80,
+                min: ms(40),
+            },
+            ..ButtonConfig::default()
+        },
+    ];
+    let mut flash = MockFlash::new();
+    storage::save(&mut flash, SECTOR as u32, &configs).unwrap();
+
+    let mut loaded = [ButtonConfig::default(); 6];
+    assert_eq!(storage::load(&mut flash, SECTOR as u32, &mut loaded), Ok(4));
+    assert_eq!(loaded[..4], configs);
+
+    // 再次保存会先擦除扇区，较短的记录覆盖较长的记录
+    storage::save(&mut flash, SECTOR as u32, &configs[1..2]).unwrap();
+    assert_eq!(flash.erases, 2);
+    assert_eq!(storage::load(&mut flash, SECTOR as u32, &mut loaded), Ok(1));
+    assert_eq!(loaded[0], ButtonConfig::GAMING);
+}
+
+#[test]
+fn test_storage_long_durations_round_trip_or_are_rejected() {
+    let with_stuck_after = |stuck_after| ButtonConfig {
+        fault_detection: Some(FaultDetection {
+            stuck_after,
+            chatter_edges: 8,
+            chatter_window: ms(500),
+            suppress: false,
+        }),
+        ..ButtonConfig::default()
+    };
+    let mut flash = MockFlash::new();
+
+    // 一小时仍能放进 u32 微秒
+    let hour = with_stuck_after(Duration::from_secs(60 * 60));
+    storage::save(&mut flash, 0, &[hour]).unwrap();
+    let mut loaded = [ButtonConfig::default(); 1];
+    assert_eq!(storage::load(&mut flash, 0, &mut loaded), Ok(1));
+    assert_eq!(loaded[0], hour);
+
+    // 两小时放不下：保存失败而不是截断，闪存中原有的记录保持不变
+    let too_long = with_stuck_after(Duration::from_secs(2 * 60 * 60));
+    assert_eq!(
+        storage::save(&mut flash, 0, &[too_long]),
+        Err(StorageError::Record(RecordError::DurationTooLong))
+    );
+    assert_eq!(flash.erases, 1);
+    assert_eq!(storage::load(&mut flash, 0, &mut loaded), Ok(1));
+    assert_eq!(loaded[0], hour);
+}
+
+#[test]
+fn test_storage_record_is_compact() {
+    let mut buf = [0; 256];
+    let len = storage::encode(&[tuned(), tuned()], &mut buf).unwrap();
+    assert_eq!(len, storage::record_size(2));
+    assert!(len < 200, "{len}");
+
+    assert_eq!(
+        storage::encode(&[tuned(); storage::MAX_CONFIGS + 1], &mut [0; 1024]),
+        Err(RecordError::InvalidCount)
+    );
+    assert_eq!(
+        storage::encode(&[tuned()], &mut buf[..16]),
+        Err(RecordError::BufferTooSmall)
+    );
+}
+
+/// 读取粒度超过 [`storage::load`] 内部缓冲区的闪存。
+struct LargePageFlash;
+
+impl ErrorType for LargePageFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for LargePageFlash {
+    const READ_SIZE: usize = 4096;
+
+    fn read(&mut self, _offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        bytes.fill(0xFF);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        4 * Self::READ_SIZE
+    }
+}
+
+#[test]
+fn test_storage_load_rejects_read_size_larger_than_buffer() {
+    let mut configs = [ButtonConfig::default(); 1];
+    assert_eq!(
+        storage::load(&mut LargePageFlash, 0, &mut configs),
+        Err(StorageError::Record(RecordError::BufferTooSmall))
+    );
+}
+
+#[test]
+fn test_storage_erased_flash_falls_back_to_default() {
+    let mut flash = MockFlash::new();
+    let mut configs = [ButtonConfig::GAMING; 3];
+    assert_eq!(
+        storage::load(&mut flash, 0, &mut configs),
+        Err(StorageError::Record(RecordError::BadMagic))
+    );
+    assert_eq!(storage::load_or_default(&mut flash, 0, &mut configs), 0);
+    assert_eq!(configs, [ButtonConfig::default(); 3]);
+}
+
+#[test]
+fn test_storage_corruption_falls_back_to_default() {
+    let mut flash = MockFlash::new();
+    storage::save(&mut flash, 0, &[tuned(), tuned()]).unwrap();
+
+    // 模拟掉电：记录末尾的一个字节没有写入
+    flash.data[storage::record_size(2) - 1] = 0xFF;
+    let mut configs = [tuned(); 2];
+    assert_eq!(
+        storage::load(&mut flash, 0, &mut configs),
+        Err(StorageError::Record(RecordError::CrcMismatch))
+    );
+    assert_eq!(storage::load_or_default(&mut flash, 0, &mut configs), 0);
+    assert_eq!(configs, [ButtonConfig::default(); 2]);
+
+    // 只保存了部分按钮的配置时，其余按钮使用默认配置
+    storage::save(&mut flash, 0, &[tuned()]).unwrap();
+    let mut configs = [ButtonConfig::GAMING; 3];
+    assert_eq!(storage::load_or_default(&mut flash, 0, &mut configs), 1);
+    assert_eq!(
+        configs,
+        [tuned(), ButtonConfig::default(), ButtonConfig::default()]
+    );
+}
+
+#[test]
+fn test_storage_rejects_other_versions_and_invalid_configs() {
+    let mut buf = [0; 128];
+    let len = storage::encode(&[tuned()], &mut buf).unwrap();
+    let mut out = [ButtonConfig::default(); 1];
+
+    let mut newer = buf;
+    newer[2] += 1;
+    assert_eq!(
+        storage::decode(&newer[..len], &mut out),
+        Err(RecordError::UnsupportedVersion(2))
+    );
+
+    // 手动写入一条校验和正确、但参数相互矛盾的记录
+    let invalid = ButtonConfig {
+        long_press_time: ms(10),
+        ..ButtonConfig::default()
+    };
+    let len = storage::encode(&[invalid], &mut buf).unwrap();
+    assert!(matches!(
+        storage::decode(&buf[..len], &mut out),
+        Err(RecordError::InvalidConfig(_))
+    ));
+    assert_eq!(out[0], ButtonConfig::default());
+}
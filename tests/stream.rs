@@ -0,0 +1,102 @@
+mod common;
+
+use core::{future::poll_fn, pin::Pin};
+use embassy_async_button::{
+    config::ButtonConfig,
+    gpio::{ActiveLevel, GpioButton},
+    Button, ButtonEvent,
+};
+use embassy_time::{Duration, Timer};
+use futures_core::Stream;
+use tokio::sync::watch;
+
+use common::MockPin;
+
+// --- Test Harness (测试工具) ---
+
+/// 取出流的下一个元素，相当于 `StreamExt::next`。
+async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// 双击，随后长按并保持一个周期。
+async fn gesture_generator(tx: watch::Sender<bool>) {
+    let config = ButtonConfig::default();
+    Timer::after(Duration::from_millis(50)).await;
+    for _ in 0..2 {
+        tx.send(false).unwrap();
+        Timer::after(Duration::from_millis(50)).await;
+        tx.send(true).unwrap();
+        Timer::after(Duration::from_millis(100)).await;
+    }
+    Timer::after(config.multi_click_window).await;
+    tx.send(false).unwrap();
+    Timer::after(
+        config.long_press_time + config.long_press_hold_interval + Duration::from_millis(50),
+    )
+    .await;
+    tx.send(true).unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_yields_events() {
+    let (tx, rx) = watch::channel(true);
+    let button = Button::new(
+        GpioButton::new(MockPin { rx }, ActiveLevel::Low),
+        ButtonConfig::default(),
+    );
+    let mut stream = core::pin::pin!(button.into_stream());
+
+    let validator = async {
+        let mut events = Vec::new();
+        for _ in 0..4 {
+            let event = embassy_time::with_timeout(Duration::from_secs(2), next(&mut stream))
+                .await
+                .expect("测试超时，未等到事件");
+            events.push(event.unwrap());
+        }
+        assert_eq!(
+            events,
+            [
+                ButtonEvent::DoubleClick,
+                ButtonEvent::LongPressStart,
+                ButtonEvent::LongPressHold { missed: 0 },
+                ButtonEvent::LongPressRelease { level: 0 },
+            ]
+        );
+    };
+    tokio::join!(gesture_generator(tx), validator);
+}
+
+#[tokio::test]
+async fn test_stream_dropped_polls_keep_pending_gesture() {
+    let (tx, rx) = watch::channel(true);
+    let mut button = Button::new(
+        GpioButton::new(MockPin { rx }, ActiveLevel::Low),
+        ButtonConfig::default(),
+    );
+
+    let validator = async {
+        {
+            let mut stream = core::pin::pin!(button.stream());
+            // 消费者每 20ms 就放弃一次等待，期间完成的双击不能被拆成单击或丢失
+            let mut timeouts = 0;
+            let event = loop {
+                match embassy_time::with_timeout(Duration::from_millis(20), next(&mut stream)).await
+                {
+                    Ok(event) => break event.unwrap(),
+                    Err(_) => timeouts += 1,
+                }
+            };
+            assert_eq!(event, ButtonEvent::DoubleClick);
+            assert!(timeouts > 10, "{timeouts}");
+        }
+
+        // 丢弃流之后，按钮继续识别后续的长按
+        let event = embassy_time::with_timeout(Duration::from_secs(2), button.next_event())
+            .await
+            .expect("测试超时，未等到长按事件");
+        assert_eq!(event, ButtonEvent::LongPressStart);
+    };
+    tokio::join!(gesture_generator(tx), validator);
+}
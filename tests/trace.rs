@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+
+use embassy_async_button::{ButtonConfig, ButtonEvent, ButtonFsm};
+use embassy_time::Instant;
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// 把所有日志行收集起来的测试记录器。
+struct Collector(Mutex<Vec<String>>);
+
+impl Log for Collector {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Collector = Collector(Mutex::new(Vec::new()));
+
+fn ms(t: u64) -> Instant {
+    Instant::from_millis(t)
+}
+
+#[test]
+fn test_trace_logs_transitions_with_reason() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let mut fsm = ButtonFsm::new(ButtonConfig::default());
+    fsm.on_startup(false, ms(0));
+    // 一次 5ms 的抖动，随后是一次双击
+    let edges = [
+        (100, true),
+        (105, false),
+        (200, true),
+        (250, false),
+        (350, true),
+        (400, false),
+    ];
+    let mut events = Vec::new();
+    for (t, pressed) in edges {
+        while let Some(event) = fsm.poll(ms(t)) {
+            events.push(event);
+        }
+        fsm.on_edge(pressed, ms(t));
+    }
+    while let Some(event) = fsm.poll(ms(1000)) {
+        events.push(event);
+    }
+    assert_eq!(events, [ButtonEvent::DoubleClick]);
+
+    let lines = LOGGER.0.lock().unwrap();
+    let find = |needle: &str| {
+        lines
+            .iter()
+            .position(|line| line.contains(needle))
+            .unwrap_or_else(|| panic!("没有找到 {needle:?}：{lines:#?}"))
+    };
+    assert!(lines[find("(Startup)")].starts_with("button @0ms: Idle -> Idle"));
+    assert!(lines[find("(DebounceRejected)")].starts_with("button @105ms: PressDebouncing"));
+    let confirmed = find("@220ms: PressDebouncing");
+    assert!(lines[confirmed].ends_with("(DebounceConfirmed)"));
+    let expired = find("(WindowExpired)");
+    assert!(lines[expired].starts_with("button @650ms: WaitingForMultiClick { count: 2"));
+    assert_eq!(lines[expired + 1], "button @650ms: emit DoubleClick");
+    assert!(confirmed < expired);
+}